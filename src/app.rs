@@ -1,28 +1,63 @@
 use bevy::{prelude::*, window::close_on_esc};
 
-use crate::game::camera::GameCameraFollowMode;
+use crate::{
+    game::simulation_data_loaded,
+    input::{ActionInputPlugin, InputAction},
+    settings::SettingsPlugin,
+};
 
-#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+#[derive(States, Debug, Clone, Default, Eq, PartialEq, Hash)]
 pub enum AppState {
+    #[default]
     MainMenu,
     InGame,
     Paused,
+    GameOver,
 }
 
-#[derive(Resource)]
-pub struct Settings {
-    camera_follow_mode: GameCameraFollowMode,
-}
+/// Value of a `--name value` or `--name=value` command line argument.
+pub fn launch_arg(name: &str) -> Option<String> {
+    let mut args = std::env::args().skip(1);
+    let prefix = format!("{name}=");
+
+    while let Some(arg) = args.next() {
+        if arg == name {
+            return args.next();
+        }
 
-impl Settings {
-    pub fn new() -> Self {
-        Self {
-            camera_follow_mode: GameCameraFollowMode::Sticky,
+        if let Some(value) = arg.strip_prefix(&prefix) {
+            return Some(value.to_owned());
         }
     }
 
-    pub fn camera_follow_mode(&self) -> &GameCameraFollowMode {
-        &self.camera_follow_mode
+    None
+}
+
+fn start_game(actions: Res<Input<InputAction>>, mut next_state: ResMut<NextState<AppState>>) {
+    if actions.just_pressed(InputAction::Confirm) {
+        next_state.set(AppState::InGame);
+    }
+}
+
+fn restart_game(actions: Res<Input<InputAction>>, mut next_state: ResMut<NextState<AppState>>) {
+    if actions.just_pressed(InputAction::Confirm) {
+        next_state.set(AppState::InGame);
+    }
+}
+
+fn toggle_pause(
+    actions: Res<Input<InputAction>>,
+    state: Res<State<AppState>>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    if !actions.just_pressed(InputAction::Pause) {
+        return;
+    }
+
+    match state.get() {
+        AppState::InGame => next_state.set(AppState::Paused),
+        AppState::Paused => next_state.set(AppState::InGame),
+        AppState::MainMenu | AppState::GameOver => {}
     }
 }
 
@@ -30,7 +65,17 @@ pub struct AppPlugin;
 
 impl Plugin for AppPlugin {
     fn build(&self, app: &mut App) {
-        app.insert_resource(Settings::new());
+        app.add_state::<AppState>();
+        app.add_plugins((SettingsPlugin, ActionInputPlugin));
+        app.add_systems(
+            Update,
+            start_game.run_if(in_state(AppState::MainMenu).and_then(simulation_data_loaded)),
+        );
+        app.add_systems(Update, restart_game.run_if(in_state(AppState::GameOver)));
+        app.add_systems(
+            Update,
+            toggle_pause.run_if(in_state(AppState::InGame).or_else(in_state(AppState::Paused))),
+        );
         #[cfg(debug_assertions)]
         app.add_systems(Update, close_on_esc);
     }
@@ -0,0 +1,486 @@
+use std::collections::BTreeMap;
+
+use bevy::{
+    asset::{AssetLoader, LoadContext, LoadedAsset},
+    prelude::*,
+    reflect::{TypePath, TypeUuid},
+    utils::BoxedFuture,
+};
+use serde::Deserialize;
+use thiserror::Error;
+
+use super::{enemy::Enemy, health::Health, physics::Collider, pickup::Reward};
+
+const ENEMY_ARCHETYPE_DIRECTORY: &str = "enemies";
+
+/// How strongly each steering behaviour pulls, optional in archetype files.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct SteeringWeights {
+    seek: f32,
+    separation: f32,
+    separation_radius: f32,
+    arrival_radius: f32,
+}
+
+impl SteeringWeights {
+    pub fn seek(&self) -> f32 {
+        self.seek
+    }
+
+    pub fn separation(&self) -> f32 {
+        self.separation
+    }
+
+    /// Neighbours closer than this push the enemy away.
+    pub fn separation_radius(&self) -> f32 {
+        self.separation_radius
+    }
+
+    /// Distance over which the enemy slows down as it reaches melee range.
+    pub fn arrival_radius(&self) -> f32 {
+        self.arrival_radius
+    }
+}
+
+impl Default for SteeringWeights {
+    fn default() -> Self {
+        Self {
+            seek: 1.0,
+            separation: 1.5,
+            separation_radius: 64.0,
+            arrival_radius: 64.0,
+        }
+    }
+}
+
+/// Where an archetype may appear, optional in archetype files.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct SpawnRules {
+    off_screen: bool,
+    min_player_distance: f32,
+}
+
+impl SpawnRules {
+    /// Whether the enemy must appear outside the player's view.
+    pub fn off_screen(&self) -> bool {
+        self.off_screen
+    }
+
+    pub fn min_player_distance(&self) -> f32 {
+        self.min_player_distance
+    }
+}
+
+impl Default for SpawnRules {
+    fn default() -> Self {
+        Self {
+            off_screen: true,
+            min_player_distance: 200.0,
+        }
+    }
+}
+
+/// Optional in archetype files, enemies without it only fight up close.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub struct RangedAttack {
+    preferred_distance: f32,
+    cooldown: f32,
+    projectile_speed: f32,
+    projectile_damage: i32,
+    projectile_radius: f32,
+    #[serde(default)]
+    lead: f32,
+    #[serde(default = "default_volley")]
+    volley: u32,
+    #[serde(default)]
+    spread: f32,
+    #[serde(default = "default_range")]
+    range: f32,
+}
+
+fn default_volley() -> u32 {
+    1
+}
+
+fn default_range() -> f32 {
+    640.0
+}
+
+impl RangedAttack {
+    /// The enemy backs off when closer than this and closes in when further.
+    pub fn preferred_distance(&self) -> f32 {
+        self.preferred_distance
+    }
+
+    /// Seconds between shots.
+    pub fn cooldown(&self) -> f32 {
+        self.cooldown
+    }
+
+    pub fn projectile_speed(&self) -> f32 {
+        self.projectile_speed
+    }
+
+    pub fn projectile_damage(&self) -> i32 {
+        self.projectile_damage
+    }
+
+    pub fn projectile_radius(&self) -> f32 {
+        self.projectile_radius
+    }
+
+    /// How far to aim ahead of a moving player, from 0 for straight at them to 1 for a perfect
+    /// intercept.
+    pub fn lead(&self) -> f32 {
+        self.lead.clamp(0.0, 1.0)
+    }
+
+    /// Projectiles per shot.
+    pub fn volley(&self) -> u32 {
+        self.volley.max(1)
+    }
+
+    /// Radians a volley fans out over, centred on the aim.
+    pub fn spread(&self) -> f32 {
+        self.spread
+    }
+
+    /// How far projectiles fly before they're gone.
+    pub fn range(&self) -> f32 {
+        self.range
+    }
+}
+
+/// Minions a boss calls in every `interval` seconds while in a phase.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct BossSummon {
+    archetype: String,
+    count: u32,
+    interval: f32,
+}
+
+impl BossSummon {
+    pub fn archetype(&self) -> &str {
+        &self.archetype
+    }
+
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+
+    pub fn interval(&self) -> f32 {
+        self.interval
+    }
+}
+
+/// Entered once the boss's health fraction drops below `below`. Anything left out keeps the
+/// archetype's own value rather than the previous phase's.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct BossPhase {
+    below: f32,
+    #[serde(default)]
+    speed: Option<f32>,
+    #[serde(default)]
+    melee_cooldown: Option<f32>,
+    #[serde(default)]
+    ranged: Option<RangedAttack>,
+    #[serde(default)]
+    summon: Option<BossSummon>,
+}
+
+impl BossPhase {
+    pub fn below(&self) -> f32 {
+        self.below
+    }
+
+    pub fn summon(&self) -> Option<&BossSummon> {
+        self.summon.as_ref()
+    }
+}
+
+/// Optional in archetype files, only bosses have it.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct BossRules {
+    name: String,
+    #[serde(default)]
+    phases: Vec<BossPhase>,
+    #[serde(default)]
+    rewards: Vec<Reward>,
+}
+
+impl BossRules {
+    /// Shown when the boss arrives and over its health bar.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The phase a boss at this fraction of its health should be in, 0 before the first
+    /// threshold. Phases are listed from the highest threshold down.
+    pub fn phase_at(&self, health_fraction: f32) -> usize {
+        self.phases
+            .iter()
+            .take_while(|phase| health_fraction < phase.below())
+            .count()
+    }
+
+    /// Phases count from 1, phase 0 is the archetype as written.
+    pub fn phase(&self, phase: usize) -> Option<&BossPhase> {
+        self.phases.get(phase.checked_sub(1)?)
+    }
+
+    /// Always dropped where the boss dies.
+    pub fn rewards(&self) -> &[Reward] {
+        &self.rewards
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum EnemyArchetypeError {
+    #[error("could not parse enemy archetype: {0}")]
+    Parse(#[from] ron::error::SpannedError),
+}
+
+/// Everything that makes one kind of enemy different from another, loaded from
+/// `assets/enemies/*.enemy.ron` so new enemies don't need a recompile.
+#[derive(Debug, Clone, PartialEq, Deserialize, TypeUuid, TypePath)]
+#[uuid = "5b0e8f4a-1c7d-4b4e-9a55-6f2d1e3c8b71"]
+pub struct EnemyArchetype {
+    id: String,
+    sprite: String,
+    idle_tag: String,
+    speed: f32,
+    health: i32,
+    collider_radius: f32,
+    melee_damage: i32,
+    melee_range: f32,
+    melee_cooldown: f32,
+    #[serde(default = "default_melee_windup")]
+    melee_windup: f32,
+    #[serde(default = "default_melee_recovery")]
+    melee_recovery: f32,
+    scale: f32,
+    #[serde(default)]
+    steering: SteeringWeights,
+    #[serde(default)]
+    ranged: Option<RangedAttack>,
+    #[serde(default)]
+    spawn: SpawnRules,
+    #[serde(default)]
+    boss: Option<BossRules>,
+}
+
+fn default_melee_windup() -> f32 {
+    0.4
+}
+
+fn default_melee_recovery() -> f32 {
+    0.3
+}
+
+impl EnemyArchetype {
+    pub fn from_ron(ron: &str) -> Result<Self, EnemyArchetypeError> {
+        Ok(ron::from_str(ron)?)
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Aseprite file, relative to the assets directory.
+    pub fn sprite(&self) -> &str {
+        &self.sprite
+    }
+
+    pub fn idle_tag(&self) -> &str {
+        &self.idle_tag
+    }
+
+    pub fn speed(&self) -> f32 {
+        self.speed
+    }
+
+    pub fn health(&self) -> i32 {
+        self.health
+    }
+
+    pub fn collider_radius(&self) -> f32 {
+        self.collider_radius
+    }
+
+    pub fn melee_damage(&self) -> i32 {
+        self.melee_damage
+    }
+
+    pub fn melee_range(&self) -> f32 {
+        self.melee_range
+    }
+
+    /// Seconds between melee attacks.
+    pub fn melee_cooldown(&self) -> f32 {
+        self.melee_cooldown
+    }
+
+    /// Seconds the attack is telegraphed before it lands.
+    pub fn melee_windup(&self) -> f32 {
+        self.melee_windup
+    }
+
+    /// Seconds the enemy stays rooted after striking.
+    pub fn melee_recovery(&self) -> f32 {
+        self.melee_recovery
+    }
+
+    pub fn scale(&self) -> f32 {
+        self.scale
+    }
+
+    pub fn steering(&self) -> &SteeringWeights {
+        &self.steering
+    }
+
+    pub fn ranged(&self) -> Option<&RangedAttack> {
+        self.ranged.as_ref()
+    }
+
+    pub fn spawn(&self) -> &SpawnRules {
+        &self.spawn
+    }
+
+    pub fn boss(&self) -> Option<&BossRules> {
+        self.boss.as_ref()
+    }
+
+    /// A copy with a boss phase's overrides applied, unchanged for phase 0 or non-bosses.
+    pub fn in_boss_phase(&self, phase: usize) -> Self {
+        let mut archetype = self.clone();
+
+        let Some(phase) = self.boss().and_then(|boss| boss.phase(phase)) else {
+            return archetype;
+        };
+
+        if let Some(speed) = phase.speed {
+            archetype.speed = speed;
+        }
+
+        if let Some(melee_cooldown) = phase.melee_cooldown {
+            archetype.melee_cooldown = melee_cooldown;
+        }
+
+        if let Some(ranged) = phase.ranged {
+            archetype.ranged = Some(ranged);
+        }
+
+        archetype
+    }
+}
+
+/// Every archetype the simulation can spawn, by id. Kept in sync with the loaded assets, but it's
+/// a plain resource so headless runs can fill it straight from the files.
+#[derive(Resource, Debug, Default)]
+pub struct EnemyArchetypes {
+    archetypes: BTreeMap<String, EnemyArchetype>,
+}
+
+impl EnemyArchetypes {
+    pub fn new(archetypes: impl IntoIterator<Item = EnemyArchetype>) -> Self {
+        Self {
+            archetypes: archetypes
+                .into_iter()
+                .map(|archetype| (archetype.id().to_string(), archetype))
+                .collect(),
+        }
+    }
+
+    pub fn get(&self, id: &str) -> Option<&EnemyArchetype> {
+        self.archetypes.get(id)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.archetypes.is_empty()
+    }
+
+    /// In id order, so picking from it with the run's RNG is reproducible.
+    pub fn iter(&self) -> impl Iterator<Item = &EnemyArchetype> {
+        self.archetypes.values()
+    }
+}
+
+#[derive(Default)]
+pub struct EnemyArchetypeLoader;
+
+impl AssetLoader for EnemyArchetypeLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let archetype = EnemyArchetype::from_ron(std::str::from_utf8(bytes)?)?;
+            load_context.set_default_asset(LoadedAsset::new(archetype));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["enemy.ron"]
+    }
+}
+
+/// Keeps the archetype files loaded, and watched for changes when hot reloading is on.
+#[derive(Resource)]
+struct EnemyArchetypeHandles(Vec<HandleUntyped>);
+
+fn load_enemy_archetypes(mut commands: Commands, asset_server: Res<AssetServer>) {
+    match asset_server.load_folder(ENEMY_ARCHETYPE_DIRECTORY) {
+        Ok(handles) => commands.insert_resource(EnemyArchetypeHandles(handles)),
+        Err(error) => error!("Failed to load enemy archetypes: {error}"),
+    }
+}
+
+/// Rebuilds [`EnemyArchetypes`] whenever a file is added, edited or removed, and hands the new
+/// stats to enemies that are already alive.
+fn sync_enemy_archetypes(
+    mut asset_events: EventReader<AssetEvent<EnemyArchetype>>,
+    assets: Res<Assets<EnemyArchetype>>,
+    mut archetypes: ResMut<EnemyArchetypes>,
+    mut enemy_query: Query<(&mut Enemy, &mut Health, &mut Collider)>,
+) {
+    if asset_events.is_empty() {
+        return;
+    }
+    asset_events.clear();
+
+    *archetypes = EnemyArchetypes::new(assets.iter().map(|(_, archetype)| archetype.clone()));
+
+    // Live enemies pick up the new stats too, not just the ones spawned from now on
+    for (mut enemy, mut health, mut collider) in enemy_query.iter_mut() {
+        let Some(archetype) = archetypes.get(enemy.archetype().id()) else {
+            continue;
+        };
+
+        if archetype != enemy.archetype() {
+            enemy.set_archetype(archetype.clone());
+        }
+
+        if health.max() != archetype.health() {
+            health.set_max(archetype.health());
+        }
+
+        if collider.radius() != archetype.collider_radius() {
+            *collider = Collider::circle(archetype.collider_radius());
+        }
+    }
+}
+
+pub struct EnemyArchetypeAssetsPlugin;
+
+impl Plugin for EnemyArchetypeAssetsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_asset::<EnemyArchetype>();
+        app.init_asset_loader::<EnemyArchetypeLoader>();
+        app.add_systems(Startup, load_enemy_archetypes);
+        app.add_systems(Update, sync_enemy_archetypes);
+    }
+}
@@ -0,0 +1,402 @@
+use std::{f32::consts::TAU, time::Duration};
+
+use bevy::prelude::*;
+
+use crate::app::AppState;
+
+use super::{
+    archetype::{EnemyArchetype, EnemyArchetypes},
+    camera::CameraInterest,
+    clock::SimulationClock,
+    despawn_all,
+    enemy::{pool_dead_enemies, spawn_enemy, Enemy, EnemyBundle},
+    health::{take_damage, Health},
+    pickup::PickupBundle,
+    rng::RunRng,
+    spawn::{find_position_around, EnemySpawner},
+    wave::{direct_bosses, WaveSchedule},
+    BossArrived, BossPhaseChanged, CameraZoomRequested, EntityDied, GameSet,
+};
+
+/// Gap left between the boss's collider and each summoned minion's.
+const SUMMON_MARGIN: f32 = 8.0;
+
+/// How far from where the boss fell its rewards are scattered, so they don't stack up.
+const REWARD_SCATTER: f32 = 48.0;
+
+const ARRIVAL_ZOOM: f32 = 1.5;
+const ARRIVAL_ZOOM_SECONDS: f32 = 2.5;
+const PHASE_ZOOM: f32 = 1.25;
+const PHASE_ZOOM_SECONDS: f32 = 1.0;
+const BANNER_SECONDS: f32 = 3.0;
+const INTEREST_WEIGHT: f32 = 1.0;
+const INTEREST_RADIUS: f32 = 640.0;
+
+/// An enemy whose archetype has boss rules. Its [`Enemy`] archetype is swapped for the current
+/// phase's version as health thresholds are crossed. Summon times are simulation clock seconds.
+#[derive(Component, Debug, Default)]
+pub struct Boss {
+    phase: usize,
+    last_summon: Option<f32>,
+}
+
+impl Boss {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 0 until the first health threshold is crossed.
+    pub fn phase(&self) -> usize {
+        self.phase
+    }
+}
+
+/// Bosses are rare enough that they skip the [`EnemyPool`](super::enemy::EnemyPool).
+pub fn spawn_boss(commands: &mut Commands, archetype: &EnemyArchetype, position: Vec3) -> Entity {
+    commands
+        .spawn((EnemyBundle::new(archetype, position), Boss::new()))
+        .id()
+}
+
+/// Phases only ever move forward, a boss doesn't calm down again.
+fn update_boss_phases(
+    archetypes: Res<EnemyArchetypes>,
+    mut boss_query: Query<(Entity, &mut Boss, &mut Enemy, &Health)>,
+    mut boss_phase_events: EventWriter<BossPhaseChanged>,
+) {
+    for (entity, mut boss, mut enemy, health) in boss_query.iter_mut() {
+        let Some(base) = archetypes.get(enemy.archetype().id()) else {
+            continue;
+        };
+
+        let Some(rules) = base.boss() else {
+            continue;
+        };
+
+        let phase = rules.phase_at(health.health_percentage()).max(boss.phase);
+
+        if phase != boss.phase {
+            boss.phase = phase;
+            boss.last_summon = None;
+            boss_phase_events.send(BossPhaseChanged::new(entity, phase));
+        } else if !archetypes.is_changed() {
+            continue;
+        }
+
+        // Hot reloading hands live enemies the base archetype, so this reapplies the phase too
+        let archetype = base.in_boss_phase(phase);
+        if &archetype != enemy.archetype() {
+            enemy.set_archetype(archetype);
+        }
+    }
+}
+
+/// Minions appear in a ring around the boss as soon as a phase starts and every interval after,
+/// as long as the population cap allows. Any that can't stand where the ring puts them are moved
+/// around the boss to somewhere that fits, or left out of this summon.
+fn boss_summons(
+    clock: Res<SimulationClock>,
+    schedule: Res<WaveSchedule>,
+    mut spawner: EnemySpawner,
+    mut boss_query: Query<(&mut Boss, &Enemy, &Transform)>,
+    enemy_query: Query<(), With<Enemy>>,
+) {
+    let now = clock.elapsed_seconds();
+    let mut alive = enemy_query.iter().count() as u32;
+    let mut occupied = spawner.occupied();
+
+    for (mut boss, enemy, transform) in boss_query.iter_mut() {
+        let Some(summon) = enemy
+            .archetype()
+            .boss()
+            .and_then(|rules| rules.phase(boss.phase))
+            .and_then(|phase| phase.summon())
+        else {
+            continue;
+        };
+
+        if boss
+            .last_summon
+            .is_some_and(|last_summon| now - last_summon < summon.interval())
+        {
+            continue;
+        }
+
+        boss.last_summon = Some(now);
+
+        let Some(minion) = spawner.archetypes.get(summon.archetype()) else {
+            warn!("No enemy archetype with id {}", summon.archetype());
+            continue;
+        };
+
+        let distance =
+            enemy.archetype().collider_radius() + minion.collider_radius() + SUMMON_MARGIN;
+
+        for index in 0..summon.count() {
+            if alive >= schedule.population().max_enemies() {
+                break;
+            }
+
+            let Some(position) = find_position_around(
+                &mut spawner.rng,
+                &spawner.spawn_area,
+                minion.collider_radius(),
+                transform.translation,
+                distance,
+                TAU * index as f32 / summon.count() as f32,
+                &occupied,
+            ) else {
+                continue;
+            };
+
+            spawn_enemy(&mut spawner.commands, &mut spawner.pool, minion, position);
+            occupied.push((position, minion.collider_radius()));
+            alive += 1;
+        }
+    }
+}
+
+fn drop_boss_rewards(
+    mut commands: Commands,
+    mut entity_died_events: EventReader<EntityDied>,
+    boss_query: Query<&Enemy, With<Boss>>,
+) {
+    for event in entity_died_events.iter() {
+        let Some(rewards) = boss_query
+            .get(event.entity())
+            .ok()
+            .and_then(|enemy| enemy.archetype().boss())
+            .map(|rules| rules.rewards())
+        else {
+            continue;
+        };
+
+        for (index, reward) in rewards.iter().enumerate() {
+            let offset = if rewards.len() > 1 {
+                Vec2::from_angle(TAU * index as f32 / rewards.len() as f32) * REWARD_SCATTER
+            } else {
+                Vec2::ZERO
+            };
+
+            let position = event.position() + offset.extend(0.0);
+            commands.spawn(PickupBundle::new(*reward, position));
+        }
+    }
+}
+
+/// Screen-wide bar along the bottom for one boss, gone once the boss is.
+#[derive(Component)]
+struct BossHealthBar {
+    boss: Entity,
+}
+
+#[derive(Component)]
+struct BossHealthBarFill {
+    boss: Entity,
+}
+
+#[derive(Component)]
+struct BossBanner {
+    timer: Timer,
+}
+
+fn spawn_boss_banner(commands: &mut Commands, name: &str) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.),
+                    top: Val::Percent(15.),
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                ..default()
+            },
+            BossBanner {
+                timer: Timer::from_seconds(BANNER_SECONDS, TimerMode::Once),
+            },
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                format!("{name} approaches"),
+                TextStyle {
+                    font_size: 48.,
+                    color: Color::rgb(1.0, 0.3, 0.2),
+                    ..default()
+                },
+            ));
+        });
+}
+
+fn spawn_boss_health_bar(commands: &mut Commands, boss: Entity, name: &str) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    left: Val::Percent(10.),
+                    right: Val::Percent(10.),
+                    bottom: Val::Px(24.),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    row_gap: Val::Px(4.),
+                    ..default()
+                },
+                ..default()
+            },
+            BossHealthBar { boss },
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                name,
+                TextStyle {
+                    font_size: 24.,
+                    color: Color::WHITE,
+                    ..default()
+                },
+            ));
+
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        width: Val::Percent(100.),
+                        height: Val::Px(12.),
+                        ..default()
+                    },
+                    background_color: Color::rgba(0.0, 0.0, 0.0, 0.6).into(),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent.spawn((
+                        NodeBundle {
+                            style: Style {
+                                width: Val::Percent(100.),
+                                height: Val::Percent(100.),
+                                ..default()
+                            },
+                            background_color: Color::rgb(0.8, 0.1, 0.1).into(),
+                            ..default()
+                        },
+                        BossHealthBarFill { boss },
+                    ));
+                });
+        });
+}
+
+/// Announces the boss, pulls the camera out to show it and keeps it framed while the player is
+/// nearby.
+fn announce_bosses(
+    mut commands: Commands,
+    mut boss_arrived_events: EventReader<BossArrived>,
+    boss_query: Query<&Enemy, With<Boss>>,
+    mut zoom_requested_events: EventWriter<CameraZoomRequested>,
+) {
+    for event in boss_arrived_events.iter() {
+        let Some(rules) = boss_query
+            .get(event.entity())
+            .ok()
+            .and_then(|enemy| enemy.archetype().boss())
+        else {
+            continue;
+        };
+
+        commands
+            .entity(event.entity())
+            .insert(CameraInterest::new(INTEREST_WEIGHT, INTEREST_RADIUS));
+
+        zoom_requested_events.send(CameraZoomRequested::new(
+            ARRIVAL_ZOOM,
+            Duration::from_secs_f32(ARRIVAL_ZOOM_SECONDS),
+        ));
+
+        spawn_boss_banner(&mut commands, rules.name());
+        spawn_boss_health_bar(&mut commands, event.entity(), rules.name());
+    }
+}
+
+fn zoom_on_boss_phase(
+    mut boss_phase_events: EventReader<BossPhaseChanged>,
+    mut zoom_requested_events: EventWriter<CameraZoomRequested>,
+) {
+    for _ in boss_phase_events.iter() {
+        zoom_requested_events.send(CameraZoomRequested::new(
+            PHASE_ZOOM,
+            Duration::from_secs_f32(PHASE_ZOOM_SECONDS),
+        ));
+    }
+}
+
+fn expire_boss_banners(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut banner_query: Query<(Entity, &mut BossBanner)>,
+) {
+    for (entity, mut banner) in banner_query.iter_mut() {
+        if banner.timer.tick(time.delta()).finished() {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+fn update_boss_health_bars(
+    mut commands: Commands,
+    bar_query: Query<(Entity, &BossHealthBar)>,
+    mut fill_query: Query<(&BossHealthBarFill, &mut Style)>,
+    health_query: Query<&Health, With<Boss>>,
+) {
+    for (entity, bar) in bar_query.iter() {
+        if !health_query.contains(bar.boss) {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+
+    for (fill, mut style) in fill_query.iter_mut() {
+        if let Ok(health) = health_query.get(fill.boss) {
+            style.width = Val::Percent(100. * health.health_percentage().clamp(0.0, 1.0));
+        }
+    }
+}
+
+pub struct BossPlugin;
+
+impl Plugin for BossPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            FixedUpdate,
+            (boss_summons)
+                .after(direct_bosses)
+                .run_if(resource_exists::<RunRng>())
+                .in_set(GameSet::Ai),
+        );
+        app.add_systems(
+            FixedUpdate,
+            (update_boss_phases, drop_boss_rewards)
+                .chain()
+                .after(take_damage)
+                .before(pool_dead_enemies)
+                .in_set(GameSet::ResolveDamage),
+        );
+    }
+}
+
+pub struct BossVisualsPlugin;
+
+impl Plugin for BossVisualsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (
+                announce_bosses,
+                zoom_on_boss_phase,
+                expire_boss_banners,
+                update_boss_health_bars,
+            )
+                .in_set(GameSet::Ui),
+        );
+        app.add_systems(
+            OnEnter(AppState::GameOver),
+            (despawn_all::<BossHealthBar>, despawn_all::<BossBanner>),
+        );
+    }
+}
@@ -1,25 +1,274 @@
-use bevy::{prelude::*, window::PrimaryWindow};
+use std::ops::{Add, Mul, Sub};
 
-use crate::app::Settings;
+use bevy::{prelude::*, render::camera::ScalingMode, window::PrimaryWindow};
+use bevy_ecs_tilemap::prelude::{TilemapGridSize, TilemapSize, TilemapTileSize};
+use serde::{Deserialize, Serialize};
 
-use super::{calculate_player_direction_from_mouse, player::Player, GameSet};
+use crate::settings::Settings;
+
+use super::{
+    enemy::Enemy,
+    intent::PlayerIntent,
+    physics::Velocity,
+    player::Player,
+    spawn::{SpawnArea, VIEW_SIZE},
+    CameraZoomRequested, EntityDied, EntityTookDamage, GameSet, WeaponFired,
+};
 
 pub const CAMERA_OFFSET_FROM_PLAYER: f32 = 64.0;
 
+const SHAKE_MAX_OFFSET: f32 = 16.0;
+const SHAKE_MAX_ROTATION: f32 = 0.05;
+const SHAKE_TRAUMA_DECAY_PER_SECOND: f32 = 1.5;
+
+const LOOK_AHEAD_SECONDS: f32 = 0.4;
+const ROOM_TRANSITION_SECONDS: f32 = 0.4;
+
+const INTEREST_PLAYER_MARGIN: f32 = 64.0;
+const INTEREST_MAX_PULL: f32 = 160.0;
+
+const ZOOM_HALF_LIFE: f32 = 0.75;
+const ZOOM_ENEMY_RADIUS: f32 = 480.0;
+const ZOOM_ENEMIES_FOR_MAX: f32 = 12.0;
+
+const PLAYER_HIT_TRAUMA: f32 = 0.4;
+const NEARBY_DEATH_TRAUMA: f32 = 0.2;
+const NEARBY_DEATH_RADIUS: f32 = 256.0;
+
 #[derive(Component, Debug)]
 pub struct GameCamera;
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum GameCameraFollowMode {
+    /// Centered on the player.
     Sticky,
+    /// Pushed towards where the player is aiming.
     Leading,
+    /// Centered on the player, but follows on a critically damped spring so it eases in and out.
+    Spring,
+    /// Holds still until the player leaves a rectangle around the middle of the screen.
+    DeadZone,
+    /// Pushed ahead in the direction the player is moving, no cursor needed.
+    LookAhead,
+    /// Frames whichever [`CameraRoom`] the player is in, sliding across when they change room.
+    /// Falls back to following the player outside of every room.
+    RoomLocked,
+}
+
+/// Tuning for how the camera chases its goal, all of it measured in seconds so it feels the
+/// same at any frame rate.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CameraSmoothing {
+    half_life: f32,
+    spring_frequency: f32,
+    dead_zone: Vec2,
+}
+
+impl CameraSmoothing {
+    pub fn new(half_life: f32, spring_frequency: f32, dead_zone: Vec2) -> Self {
+        Self {
+            half_life,
+            spring_frequency,
+            dead_zone,
+        }
+    }
+
+    /// Seconds for the camera to close half the distance to its goal.
+    pub fn half_life(&self) -> f32 {
+        self.half_life.max(0.0)
+    }
+
+    /// Stiffness of the spring in radians per second, higher settles faster.
+    pub fn spring_frequency(&self) -> f32 {
+        self.spring_frequency.max(0.0)
+    }
+
+    /// Full size of the dead zone rectangle in world units.
+    pub fn dead_zone(&self) -> Vec2 {
+        self.dead_zone.max(Vec2::ZERO)
+    }
+}
+
+impl Default for CameraSmoothing {
+    fn default() -> Self {
+        // A 60th of a second matches the old half the distance per frame at 60 FPS
+        Self::new(1.0 / 60.0, 12.0, Vec2::new(96.0, 64.0))
+    }
 }
 
 #[derive(Component)]
 pub struct GameCameraGoal;
 
+/// How far the camera may zoom, as orthographic scales where above 1 shows more of the world.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CameraZoomLimits {
+    min: f32,
+    max: f32,
+}
+
+impl CameraZoomLimits {
+    pub fn new(min: f32, max: f32) -> Self {
+        Self { min, max }
+    }
+
+    pub fn min(&self) -> f32 {
+        self.min.max(0.1)
+    }
+
+    pub fn max(&self) -> f32 {
+        self.max.max(self.min())
+    }
+
+    pub fn clamp(&self, scale: f32) -> f32 {
+        scale.clamp(self.min(), self.max())
+    }
+}
+
+impl Default for CameraZoomLimits {
+    fn default() -> Self {
+        Self::new(1.0, 1.75)
+    }
+}
+
+/// Zooms out as enemies crowd around the player and back in when it's quiet, unless a
+/// [`CameraZoomRequested`] is holding it somewhere else.
+#[derive(Component, Debug, Default)]
+pub struct CameraZoom {
+    requested: Option<(f32, Timer)>,
+}
+
+/// World space area the camera may show, the union of every loaded tilemap. `None` leaves the
+/// camera free, e.g. before any map has been loaded.
+#[derive(Resource, Debug, Default)]
+pub struct CameraBounds(Option<Rect>);
+
+impl CameraBounds {
+    pub fn get(&self) -> Option<Rect> {
+        self.0
+    }
+}
+
+/// Where the mouse cursor points in the world, through the game camera's projection, so it
+/// stays right however far the camera is from the player. `None` while the cursor is outside
+/// the window.
+#[derive(Resource, Debug, Default, Clone, Copy)]
+pub struct CursorWorldPosition(Option<Vec2>);
+
+impl CursorWorldPosition {
+    pub fn get(&self) -> Option<Vec2> {
+        self.0
+    }
+}
+
+/// A region the room locked camera frames on its own, e.g. one room of a dungeon. Rooms smaller
+/// than the view are centered, larger ones scroll with the player but never past their edges.
+/// Every tilemap is made a room covering itself, more can be spawned for finer regions.
+#[derive(Component, Debug)]
+pub struct CameraRoom {
+    area: Rect,
+}
+
+impl CameraRoom {
+    pub fn new(area: Rect) -> Self {
+        Self { area }
+    }
+
+    pub fn area(&self) -> Rect {
+        self.area
+    }
+}
+
+/// Pulls the camera towards an entity, e.g. a boss, chest or objective, while the player is
+/// within `radius` of it. The pull fades out towards the edge of the radius, and `weight` is
+/// relative to the player's own weight of 1.
+#[derive(Component, Debug)]
+pub struct CameraInterest {
+    weight: f32,
+    radius: f32,
+}
+
+impl CameraInterest {
+    pub fn new(weight: f32, radius: f32) -> Self {
+        Self { weight, radius }
+    }
+
+    pub fn weight(&self) -> f32 {
+        self.weight.max(0.0)
+    }
+
+    pub fn radius(&self) -> f32 {
+        self.radius
+    }
+}
+
+/// The room the camera is framing, and the slide over to it from wherever the camera was.
+#[derive(Component, Debug)]
+pub struct CameraRoomTransition {
+    room: Option<Entity>,
+    from: Vec3,
+    timer: Timer,
+}
+
+impl Default for CameraRoomTransition {
+    fn default() -> Self {
+        let mut timer = Timer::from_seconds(ROOM_TRANSITION_SECONDS, TimerMode::Once);
+        timer.set_elapsed(timer.duration());
+
+        Self {
+            room: None,
+            from: Vec3::ZERO,
+            timer,
+        }
+    }
+}
+
+/// Velocity carried between frames by the spring follow mode.
+#[derive(Component, Debug, Default)]
+pub struct GameCameraVelocity(Vec3);
+
+/// Trauma based shake. Trauma decays over time and the shake grows with its square, so small
+/// knocks barely register while big ones stack into a violent shake. The offset is removed
+/// before the camera follows its goal and reapplied after, so it never leaks into the follow.
+#[derive(Component, Debug, Default)]
+pub struct CameraShake {
+    trauma: f32,
+    elapsed: f32,
+    offset: Vec3,
+}
+
+impl CameraShake {
+    pub fn trauma(&self) -> f32 {
+        self.trauma
+    }
+
+    pub fn add_trauma(&mut self, trauma: f32) {
+        self.trauma = (self.trauma + trauma).clamp(0.0, 1.0);
+    }
+}
+
+/// Smooth deterministic noise in -1..1, different seeds give uncorrelated channels.
+fn shake_noise(time: f32, seed: f32) -> f32 {
+    ((time * 23.0 + seed).sin() + (time * 37.0 + seed * 1.7).sin()) * 0.5
+}
+
 fn setup(mut commands: Commands) {
-    commands.spawn((Camera2dBundle::default(), GameCamera));
+    // Never shows more than `max_view_size` allows for, bigger windows see the same area larger
+    let mut camera = Camera2dBundle::default();
+    camera.projection.scaling_mode = ScalingMode::AutoMax {
+        max_width: VIEW_SIZE.x,
+        max_height: VIEW_SIZE.y,
+    };
+
+    commands.spawn((
+        camera,
+        GameCamera,
+        GameCameraVelocity::default(),
+        CameraZoom::default(),
+        CameraRoomTransition::default(),
+        CameraShake::default(),
+    ));
 
     commands.spawn((
         GameCameraGoal,
@@ -30,44 +279,528 @@ fn setup(mut commands: Commands) {
     ));
 }
 
+/// Runs before the simulation ticks, using the camera as it was last rendered, which is what the
+/// player was looking at when they moved the mouse.
+fn update_cursor_world_position(
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    camera_query: Query<(&Camera, &GlobalTransform), With<GameCamera>>,
+    mut cursor: ResMut<CursorWorldPosition>,
+) {
+    cursor.0 = window_query
+        .get_single()
+        .ok()
+        .and_then(|window| window.cursor_position())
+        .zip(camera_query.get_single().ok())
+        .and_then(|(cursor_position, (camera, camera_transform))| {
+            camera.viewport_to_world_2d(camera_transform, cursor_position)
+        });
+}
+
+fn tilemap_world_rect(
+    size: &TilemapSize,
+    grid_size: &TilemapGridSize,
+    tile_size: &TilemapTileSize,
+    transform: &Transform,
+) -> Rect {
+    // Tile centers sit on the grid starting at the tilemap's origin
+    let half_tile = Vec2::new(tile_size.x, tile_size.y) / 2.0;
+    let last_tile_center = Vec2::new(
+        (size.x.max(1) - 1) as f32 * grid_size.x,
+        (size.y.max(1) - 1) as f32 * grid_size.y,
+    );
+
+    let min = transform.transform_point((-half_tile).extend(0.0));
+    let max = transform.transform_point((last_tile_center + half_tile).extend(0.0));
+
+    Rect::from_corners(min.truncate(), max.truncate())
+}
+
+fn update_camera_bounds(
+    mut bounds: ResMut<CameraBounds>,
+    tilemap_query: Query<(&TilemapSize, &TilemapGridSize, &TilemapTileSize, &Transform)>,
+    changed_tilemap_query: Query<
+        (),
+        (
+            With<TilemapSize>,
+            Or<(Changed<TilemapSize>, Changed<TilemapGridSize>, Changed<Transform>)>,
+        ),
+    >,
+    mut removed_tilemaps: RemovedComponents<TilemapSize>,
+) {
+    let removed = removed_tilemaps.iter().count() > 0;
+
+    if changed_tilemap_query.is_empty() && !removed {
+        return;
+    }
+
+    bounds.0 = tilemap_query
+        .iter()
+        .map(|(size, grid_size, tile_size, transform)| {
+            tilemap_world_rect(size, grid_size, tile_size, transform)
+        })
+        .reduce(|bounds, rect| bounds.union(rect));
+}
+
+/// Keeps a [`CameraRoom`] on every tilemap covering its world rect.
+fn sync_tilemap_rooms(
+    mut commands: Commands,
+    tilemap_query: Query<
+        (
+            Entity,
+            &TilemapSize,
+            &TilemapGridSize,
+            &TilemapTileSize,
+            &Transform,
+        ),
+        Or<(
+            Changed<TilemapSize>,
+            Changed<TilemapGridSize>,
+            Changed<TilemapTileSize>,
+            Changed<Transform>,
+        )>,
+    >,
+) {
+    for (entity, size, grid_size, tile_size, transform) in tilemap_query.iter() {
+        let area = tilemap_world_rect(size, grid_size, tile_size, transform);
+        commands.entity(entity).insert(CameraRoom::new(area));
+    }
+}
+
+/// Moves `point` as little as possible so a view of `half_view` around it stays inside `area`,
+/// centering on any axis where the area is smaller than the view.
+pub fn frame_within(point: Vec2, area: Rect, half_view: Vec2) -> Vec2 {
+    let min = area.min + half_view;
+    let max = area.max - half_view;
+    let center = area.center();
+
+    Vec2::new(
+        if min.x > max.x {
+            center.x
+        } else {
+            point.x.clamp(min.x, max.x)
+        },
+        if min.y > max.y {
+            center.y
+        } else {
+            point.y.clamp(min.y, max.y)
+        },
+    )
+}
+
+/// The most of the world around the player the camera can show with these settings: fully zoomed
+/// out and pushed as far off the player as the follow mode and interests allow. It doesn't depend
+/// on the window, frame rate or what's nearby, so spawning can use it to keep off screen enemies
+/// off screen and replays still place them the same.
+pub fn max_view_size(settings: &Settings) -> Vec2 {
+    let half_view = VIEW_SIZE / 2.0 * settings.camera_zoom_limits().max();
+    let reach = (half_view - Vec2::splat(INTEREST_PLAYER_MARGIN)).max(Vec2::ZERO);
+
+    let follow_offset = match settings.camera_follow_mode() {
+        GameCameraFollowMode::Sticky | GameCameraFollowMode::Spring => Vec2::ZERO,
+        GameCameraFollowMode::Leading | GameCameraFollowMode::LookAhead => {
+            Vec2::splat(CAMERA_OFFSET_FROM_PLAYER)
+        }
+        GameCameraFollowMode::DeadZone => settings.camera_smoothing().dead_zone() / 2.0,
+        // Rooms can frame the player anywhere that keeps them on screen
+        GameCameraFollowMode::RoomLocked => reach,
+    };
+
+    let offset = (follow_offset + Vec2::splat(INTEREST_MAX_PULL)).min(reach);
+
+    (half_view + offset) * 2.0
+}
+
+/// Weighted average of the goal and every interest in range, then pulled back if needed so the
+/// player stays on screen with a margin to spare. Interests never move the camera more than
+/// `INTEREST_MAX_PULL` from the goal, which keeps [`max_view_size`] a real bound.
+fn blend_interests<'a>(
+    goal: Vec3,
+    player_position: Vec3,
+    interests: impl Iterator<Item = (&'a Transform, &'a CameraInterest)>,
+    half_view: Vec2,
+) -> Vec3 {
+    let mut total_weight = 1.0;
+    let mut weighted_sum = goal.truncate();
+
+    for (transform, interest) in interests {
+        let position = transform.translation.truncate();
+        let distance = position.distance(player_position.truncate());
+
+        if distance >= interest.radius() {
+            continue;
+        }
+
+        let weight = interest.weight() * (1.0 - distance / interest.radius());
+
+        total_weight += weight;
+        weighted_sum += position * weight;
+    }
+
+    let pull = (weighted_sum / total_weight - goal.truncate()).clamp_length_max(INTEREST_MAX_PULL);
+    let blended = goal.truncate() + pull;
+    let reach = (half_view - Vec2::splat(INTEREST_PLAYER_MARGIN)).max(Vec2::ZERO);
+    let player = player_position.truncate();
+
+    blended.clamp(player - reach, player + reach).extend(goal.z)
+}
+
+fn update_camera_room(
+    settings: Res<Settings>,
+    player_query: Query<&Transform, With<Player>>,
+    room_query: Query<(Entity, &CameraRoom)>,
+    mut camera_query: Query<(&Transform, &mut CameraRoomTransition), With<GameCamera>>,
+) {
+    let Ok((camera_transform, mut transition)) = camera_query.get_single_mut() else {
+        return;
+    };
+
+    let room = match (settings.camera_follow_mode(), player_query.get_single()) {
+        (GameCameraFollowMode::RoomLocked, Ok(player_transform)) => {
+            let player_position = player_transform.translation.truncate();
+
+            // Stay in the current room while the player is still inside it, so overlapping
+            // doorways don't flicker between rooms
+            room_query
+                .iter()
+                .filter(|(_, room)| room.area().contains(player_position))
+                .map(|(entity, _)| entity)
+                .find(|entity| Some(*entity) == transition.room)
+                .or_else(|| {
+                    room_query
+                        .iter()
+                        .find(|(_, room)| room.area().contains(player_position))
+                        .map(|(entity, _)| entity)
+                })
+        }
+        _ => None,
+    };
+
+    if room != transition.room {
+        let is_room_locked = settings.camera_follow_mode() == &GameCameraFollowMode::RoomLocked;
+
+        transition.room = room;
+        transition.from = camera_transform.translation;
+        transition.timer.reset();
+
+        // Switching modes shouldn't play a slide
+        if !is_room_locked {
+            let duration = transition.timer.duration();
+            transition.timer.set_elapsed(duration);
+        }
+    }
+}
+
 fn update_camera_goal_position(
-    player_query: Query<&Transform, (With<Player>, Without<GameCameraGoal>)>,
+    player_query: Query<(&Transform, Option<&Velocity>), (With<Player>, Without<GameCameraGoal>)>,
     mut camera_goal_query: Query<&mut Transform, (With<GameCameraGoal>, Without<Player>)>,
-    window: Query<&Window, With<PrimaryWindow>>,
+    camera_query: Query<
+        (
+            &OrthographicProjection,
+            &CameraRoomTransition,
+            &Transform,
+            &CameraShake,
+        ),
+        (With<GameCamera>, Without<GameCameraGoal>),
+    >,
+    room_query: Query<&CameraRoom>,
+    interest_query: Query<(&Transform, &CameraInterest), Without<GameCameraGoal>>,
+    intent: Res<PlayerIntent>,
     settings: Res<Settings>,
 ) {
-    if let Ok(player_transform) = player_query.get_single() {
+    if let Ok((player_transform, player_velocity)) = player_query.get_single() {
         if let Ok(mut camera_goal_transform) = camera_goal_query.get_single_mut() {
-            if let Some(position) = window.single().cursor_position() {
-                match settings.camera_follow_mode() {
-                    GameCameraFollowMode::Sticky => {
-                        camera_goal_transform.translation = player_transform.translation;
-                    }
-                    GameCameraFollowMode::Leading => {
-                        let cursor_offset_from_center =
-                            calculate_player_direction_from_mouse(&position, window.single())
-                                .clamp_length_max(CAMERA_OFFSET_FROM_PLAYER);
-
-                        camera_goal_transform.translation =
-                            player_transform.translation + cursor_offset_from_center;
-                    }
+            let player_position = player_transform.translation;
+
+            let goal = match settings.camera_follow_mode() {
+                GameCameraFollowMode::Sticky | GameCameraFollowMode::Spring => player_position,
+                GameCameraFollowMode::Leading => {
+                    player_position + intent.aim().unwrap_or(Vec3::ZERO)
                 }
-            } else {
-                camera_goal_transform.translation = player_transform.translation;
-            }
+                GameCameraFollowMode::DeadZone => {
+                    // Measured from what's on screen rather than the last goal, which interests
+                    // may have pulled elsewhere, so the camera holds still inside the zone
+                    let center = camera_query.get_single().map_or(
+                        camera_goal_transform.translation,
+                        |(_, _, camera_transform, shake)| {
+                            camera_transform.translation - shake.offset
+                        },
+                    );
+                    let dead_zone = settings.camera_smoothing().dead_zone();
+                    let half_extents = (dead_zone / 2.0).extend(0.0);
+                    let offset = player_position - center;
+
+                    // Only the part of the offset outside the rectangle drags the goal along
+                    center + offset - offset.clamp(-half_extents, half_extents)
+                }
+                GameCameraFollowMode::LookAhead => {
+                    let velocity = player_velocity.map_or(Vec3::ZERO, |velocity| velocity.as_vec());
+
+                    player_position
+                        + (velocity * LOOK_AHEAD_SECONDS)
+                            .clamp_length_max(CAMERA_OFFSET_FROM_PLAYER)
+                }
+                GameCameraFollowMode::RoomLocked => camera_query
+                    .get_single()
+                    .ok()
+                    .and_then(|(projection, transition, _, _)| {
+                        let room = room_query.get(transition.room?).ok()?;
+                        let half_view = projection.area.size() / 2.0;
+
+                        Some(
+                            frame_within(player_position.truncate(), room.area(), half_view)
+                                .extend(player_position.z),
+                        )
+                    })
+                    .unwrap_or(player_position),
+            };
+
+            camera_goal_transform.translation = match camera_query.get_single() {
+                Ok((projection, ..)) => blend_interests(
+                    goal,
+                    player_position,
+                    interest_query.iter(),
+                    projection.area.size() / 2.0,
+                ),
+                Err(_) => goal,
+            };
+        }
+    }
+}
+
+fn update_camera_zoom(
+    time: Res<Time>,
+    settings: Res<Settings>,
+    mut zoom_requested_events: EventReader<CameraZoomRequested>,
+    player_query: Query<&Transform, With<Player>>,
+    enemy_query: Query<&Transform, With<Enemy>>,
+    mut camera_query: Query<(&mut OrthographicProjection, &mut CameraZoom), With<GameCamera>>,
+) {
+    let Ok((mut projection, mut zoom)) = camera_query.get_single_mut() else {
+        return;
+    };
+
+    let limits = settings.camera_zoom_limits();
+
+    for event in zoom_requested_events.iter() {
+        zoom.requested = Some((
+            event.scale(),
+            Timer::new(event.duration(), TimerMode::Once),
+        ));
+    }
+
+    if let Some((_, timer)) = zoom.requested.as_mut() {
+        if timer.tick(time.delta()).finished() {
+            zoom.requested = None;
+        }
+    }
+
+    let target = match (&zoom.requested, player_query.get_single()) {
+        (Some((scale, _)), _) => *scale,
+        (None, Ok(player_transform)) => {
+            let nearby_enemies = enemy_query
+                .iter()
+                .filter(|enemy_transform| {
+                    enemy_transform
+                        .translation
+                        .distance(player_transform.translation)
+                        < ZOOM_ENEMY_RADIUS
+                })
+                .count();
+
+            let intensity = (nearby_enemies as f32 / ZOOM_ENEMIES_FOR_MAX).min(1.0);
+
+            limits.min() + (limits.max() - limits.min()) * intensity
+        }
+        (None, Err(_)) => projection.scale,
+    };
+
+    let scale = damp(
+        projection.scale,
+        limits.clamp(target),
+        ZOOM_HALF_LIFE,
+        time.delta_seconds(),
+    );
+
+    if projection.scale != scale {
+        projection.scale = scale;
+    }
+}
+
+/// Keeps the goal far enough from the map edges that the view never shows past them, or centers
+/// it on any axis where the map is smaller than the view. The projection's area follows window
+/// resizes and zoom, so this is always measured against what's actually on screen.
+fn clamp_camera_goal_to_bounds(
+    bounds: Res<CameraBounds>,
+    camera_query: Query<&OrthographicProjection, With<GameCamera>>,
+    mut camera_goal_query: Query<&mut Transform, With<GameCameraGoal>>,
+) {
+    let Some(bounds) = bounds.get() else {
+        return;
+    };
+
+    if let Ok(projection) = camera_query.get_single() {
+        if let Ok(mut camera_goal_transform) = camera_goal_query.get_single_mut() {
+            let half_view = projection.area.size() / 2.0;
+            let goal = camera_goal_transform.translation;
+
+            camera_goal_transform.translation =
+                frame_within(goal.truncate(), bounds, half_view).extend(goal.z);
         }
     }
 }
 
+/// Closes the gap by a fixed fraction per second rather than per frame.
+fn damp<T>(current: T, goal: T, half_life: f32, delta: f32) -> T
+where
+    T: Copy + Add<Output = T> + Sub<Output = T> + Mul<f32, Output = T>,
+{
+    if half_life <= 0.0 {
+        return goal;
+    }
+
+    goal + (current - goal) * 0.5_f32.powf(delta / half_life)
+}
+
+/// Exact step of a critically damped spring, stable for any delta.
+fn spring(current: Vec3, goal: Vec3, velocity: &mut Vec3, frequency: f32, delta: f32) -> Vec3 {
+    let offset = current - goal;
+    let decay = (-frequency * delta).exp();
+    let impulse = (*velocity + frequency * offset) * delta;
+
+    *velocity = (*velocity - frequency * impulse) * decay;
+
+    goal + (offset + impulse) * decay
+}
+
 fn camera_move_to_goal_position(
+    time: Res<Time>,
+    settings: Res<Settings>,
     camera_goal_query: Query<&Transform, (With<GameCameraGoal>, Without<GameCamera>)>,
-    mut camera_query: Query<&mut Transform, (With<GameCamera>, Without<GameCameraGoal>)>,
+    mut camera_query: Query<
+        (
+            &mut Transform,
+            &mut GameCameraVelocity,
+            &mut CameraRoomTransition,
+        ),
+        (With<GameCamera>, Without<GameCameraGoal>),
+    >,
 ) {
     if let Ok(camera_goal_transform) = camera_goal_query.get_single() {
-        if let Ok(mut camera_transform) = camera_query.get_single_mut() {
-            let delta = camera_goal_transform.translation - camera_transform.translation;
-            camera_transform.translation += delta * 0.5;
+        if let Ok((mut camera_transform, mut velocity, mut transition)) =
+            camera_query.get_single_mut()
+        {
+            let smoothing = settings.camera_smoothing();
+            let goal = camera_goal_transform.translation;
+            let delta = time.delta_seconds();
+
+            camera_transform.translation = match settings.camera_follow_mode() {
+                GameCameraFollowMode::RoomLocked if transition.timer.percent() < 1.0 => {
+                    transition.timer.tick(time.delta());
+
+                    let t = transition.timer.percent();
+                    let eased = t * t * (3.0 - 2.0 * t);
+
+                    transition.from.lerp(goal, eased)
+                }
+                GameCameraFollowMode::Spring => spring(
+                    camera_transform.translation,
+                    goal,
+                    &mut velocity.0,
+                    smoothing.spring_frequency(),
+                    delta,
+                ),
+                _ => {
+                    velocity.0 = Vec3::ZERO;
+                    damp(
+                        camera_transform.translation,
+                        goal,
+                        smoothing.half_life(),
+                        delta,
+                    )
+                }
+            };
+        }
+    }
+}
+
+fn add_camera_trauma(
+    mut entity_took_damage_events: EventReader<EntityTookDamage>,
+    mut entity_died_events: EventReader<EntityDied>,
+    mut weapon_fired_events: EventReader<WeaponFired>,
+    player_query: Query<(Entity, &Transform), With<Player>>,
+    mut shake_query: Query<&mut CameraShake>,
+) {
+    let Ok(mut shake) = shake_query.get_single_mut() else {
+        return;
+    };
+
+    let player = player_query.get_single().ok();
+
+    for event in entity_took_damage_events.iter() {
+        if player.map(|(entity, _)| entity) == Some(event.entity()) {
+            shake.add_trauma(PLAYER_HIT_TRAUMA);
+        }
+    }
+
+    for event in entity_died_events.iter() {
+        let Some((player_entity, player_transform)) = player else {
+            continue;
+        };
+
+        if event.entity() != player_entity
+            && event.position().distance(player_transform.translation) < NEARBY_DEATH_RADIUS
+        {
+            shake.add_trauma(NEARBY_DEATH_TRAUMA);
+        }
+    }
+
+    for event in weapon_fired_events.iter() {
+        shake.add_trauma(event.recoil());
+    }
+}
+
+fn remove_camera_shake(mut camera_query: Query<(&mut Transform, &mut CameraShake)>) {
+    for (mut transform, mut shake) in camera_query.iter_mut() {
+        transform.translation -= shake.offset;
+        transform.rotation = Quat::IDENTITY;
+        shake.offset = Vec3::ZERO;
+    }
+}
+
+fn apply_camera_shake(
+    time: Res<Time>,
+    settings: Res<Settings>,
+    mut camera_query: Query<(&mut Transform, &mut CameraShake)>,
+) {
+    // Real time so hit-pause and slow-motion don't freeze the shake they're meant to sell
+    let delta = time.raw_delta_seconds();
+
+    for (mut transform, mut shake) in camera_query.iter_mut() {
+        shake.trauma = (shake.trauma - SHAKE_TRAUMA_DECAY_PER_SECOND * delta).max(0.0);
+        shake.elapsed += delta;
+
+        let strength = shake.trauma.powi(2) * settings.camera_shake();
+        if strength <= 0.0 {
+            continue;
         }
+
+        shake.offset = Vec3::new(
+            shake_noise(shake.elapsed, 0.0),
+            shake_noise(shake.elapsed, 10.0),
+            0.0,
+        ) * SHAKE_MAX_OFFSET
+            * strength;
+
+        transform.translation += shake.offset;
+        let roll = shake_noise(shake.elapsed, 20.0) * SHAKE_MAX_ROTATION * strength;
+        transform.rotation = Quat::from_rotation_z(roll);
+    }
+}
+
+/// Tells spawning where the map is, see [`SpawnArea`].
+fn sync_spawn_area(bounds: Res<CameraBounds>, mut spawn_area: ResMut<SpawnArea>) {
+    if spawn_area.walkable() != bounds.get() {
+        spawn_area.set_walkable(bounds.get());
     }
 }
 
@@ -88,10 +821,27 @@ pub struct GameCameraPlugin;
 
 impl Plugin for GameCameraPlugin {
     fn build(&self, app: &mut App) {
+        app.init_resource::<CameraBounds>();
+        app.init_resource::<CursorWorldPosition>();
         app.add_systems(Startup, (setup,));
+        app.add_systems(PreUpdate, update_cursor_world_position);
         app.add_systems(
             Update,
-            (camera_move_to_goal_position, update_camera_goal_position).in_set(GameSet::Ui),
+            (
+                update_camera_bounds,
+                sync_tilemap_rooms,
+                update_camera_zoom,
+                update_camera_room,
+                update_camera_goal_position,
+                clamp_camera_goal_to_bounds,
+                add_camera_trauma,
+                remove_camera_shake,
+                camera_move_to_goal_position,
+                apply_camera_shake,
+                sync_spawn_area,
+            )
+                .chain()
+                .in_set(GameSet::Ui),
         );
         #[cfg(debug_assertions)]
         app.add_systems(Update, (render_debug,).in_set(GameSet::Ui));
@@ -0,0 +1,138 @@
+use std::time::Duration;
+
+use bevy::{prelude::*, time::TimeSystem};
+
+use crate::{app::AppState, settings::Settings};
+
+use super::GameSet;
+
+/// Global multiplier on game time. The fixed timestep accumulates scaled `Time`, so the
+/// simulation, and anything else reading `Time`, slows down with it without per-system support.
+#[derive(Resource, Debug)]
+pub struct TimeScale {
+    scale: f32,
+    hit_pause_remaining: Duration,
+}
+
+impl TimeScale {
+    pub fn new(scale: f32) -> Self {
+        Self {
+            scale,
+            hit_pause_remaining: Duration::ZERO,
+        }
+    }
+
+    pub fn scale(&self) -> f32 {
+        self.scale
+    }
+
+    pub fn set_scale(&mut self, scale: f32) {
+        self.scale = scale.max(0.0);
+    }
+
+    /// Freezes game time for a stretch of real time, overlapping pauses don't stack.
+    pub fn hit_pause(&mut self, duration: Duration) {
+        self.hit_pause_remaining = self.hit_pause_remaining.max(duration);
+    }
+
+    pub fn is_hit_paused(&self) -> bool {
+        !self.hit_pause_remaining.is_zero()
+    }
+
+    fn effective_scale(&self) -> f32 {
+        if self.is_hit_paused() {
+            0.0
+        } else {
+            self.scale
+        }
+    }
+}
+
+impl Default for TimeScale {
+    fn default() -> Self {
+        Self::new(1.0)
+    }
+}
+
+/// Game time as the fixed-rate simulation sees it. Advances by exactly one period per tick,
+/// so anything reading it during `FixedUpdate` behaves the same at any frame rate.
+#[derive(Resource, Debug, Default)]
+pub struct SimulationClock {
+    tick: u64,
+    delta: Duration,
+    elapsed: Duration,
+}
+
+impl SimulationClock {
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+
+    pub fn delta(&self) -> Duration {
+        self.delta
+    }
+
+    pub fn delta_seconds(&self) -> f32 {
+        self.delta.as_secs_f32()
+    }
+
+    pub fn elapsed_seconds(&self) -> f32 {
+        self.elapsed.as_secs_f32()
+    }
+}
+
+fn advance_simulation_clock(fixed_time: Res<FixedTime>, mut clock: ResMut<SimulationClock>) {
+    clock.tick += 1;
+    clock.delta = fixed_time.period;
+    clock.elapsed += fixed_time.period;
+}
+
+pub fn configure_tick_rate(settings: Res<Settings>, mut fixed_time: ResMut<FixedTime>) {
+    let period = Duration::from_secs_f32(1.0 / settings.simulation_tick_rate());
+
+    if fixed_time.period != period {
+        fixed_time.period = period;
+    }
+}
+
+fn apply_time_scale(mut time: ResMut<Time>, mut time_scale: ResMut<TimeScale>) {
+    // Hit-pause counts down in real time, otherwise it would never end
+    let real_delta = time.raw_delta();
+    time_scale.hit_pause_remaining = time_scale.hit_pause_remaining.saturating_sub(real_delta);
+
+    time.set_relative_speed(time_scale.effective_scale());
+}
+
+fn pause_time(mut time: ResMut<Time>) {
+    time.pause();
+}
+
+fn unpause_time(mut time: ResMut<Time>) {
+    time.unpause();
+}
+
+fn reset_clocks(mut time_scale: ResMut<TimeScale>, mut clock: ResMut<SimulationClock>) {
+    *time_scale = TimeScale::default();
+    *clock = SimulationClock::default();
+}
+
+pub struct GameClockPlugin;
+
+impl Plugin for GameClockPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TimeScale>();
+        app.init_resource::<SimulationClock>();
+        app.add_systems(First, apply_time_scale.before(TimeSystem));
+        app.add_systems(
+            PreUpdate,
+            configure_tick_rate.run_if(resource_changed::<Settings>()),
+        );
+        app.add_systems(
+            FixedUpdate,
+            advance_simulation_clock.in_set(GameSet::BeginTick),
+        );
+        app.add_systems(OnEnter(AppState::Paused), pause_time);
+        app.add_systems(OnExit(AppState::Paused), unpause_time);
+        app.add_systems(OnEnter(AppState::GameOver), reset_clocks);
+    }
+}
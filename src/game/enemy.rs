@@ -1,101 +1,68 @@
-use std::time::Duration;
+use std::{f32::consts::TAU, time::Duration};
 
 use bevy::prelude::*;
 use bevy_aseprite::{anim::AsepriteAnimation, AsepriteBundle};
-use bevy_prng::ChaCha8Rng;
-use bevy_rand::resource::GlobalEntropy;
-use rand_core::RngCore;
+
+use crate::app::AppState;
 
 use super::{
+    archetype::{EnemyArchetype, EnemyArchetypes},
+    boss::Boss,
+    clock::SimulationClock,
+    despawn_all,
     health::{
         {Health, HealthBar},
-        spawn_health_bar,
+        spawn_health_bar, take_damage,
     },
-    physics::{Collider, Velocity},
+    physics::{Collider, InterpolatedPosition, Velocity},
     player::Player,
-    EnitityAllegence, EntityTookDamage, GameSet,
+    projectile::{projectile_hurt_entity, Projectile, ProjectileBundle, ProjectileRange},
+    EnemyMeleePhaseChanged, EnitityAllegence, EntityDied, EntityTookDamage, GameSet,
 };
 
-mod sprites {
-    use bevy_aseprite::aseprite;
-
-    aseprite!(pub TableAnim, "table.aseprite");
-}
-
-#[derive(Resource)]
-pub struct EnemySpawnConfig {
-    spawn_interval: f32,
-    spawn_difficulty: f32, // % per spawn to increase spawn rate
-    spawn_timer: Timer,
+/// Melee times are simulation clock seconds, the same clock weapons use.
+#[derive(Component)]
+pub struct Enemy {
+    archetype: EnemyArchetype,
+    last_melee: f32,
+    last_shot: f32,
 }
 
-impl EnemySpawnConfig {
-    pub fn new(spawn_interval: f32, spawn_difficulty: f32) -> Self {
+impl Enemy {
+    pub fn new(archetype: EnemyArchetype) -> Self {
         Self {
-            spawn_interval,
-            spawn_difficulty,
-            spawn_timer: Timer::from_seconds(spawn_interval, TimerMode::Repeating),
+            archetype,
+            last_melee: 0.0,
+            last_shot: 0.0,
         }
     }
 
-    pub fn tick(&mut self, delta: Duration) {
-        self.spawn_timer.tick(delta);
-    }
-
-    pub fn reset(&mut self) {
-        self.spawn_timer.reset();
-        self.spawn_interval *= 1.0 - self.spawn_difficulty;
-        dbg!(self.spawn_interval);
+    pub fn archetype(&self) -> &EnemyArchetype {
+        &self.archetype
     }
 
-    pub fn finished(&self) -> bool {
-        self.spawn_timer.finished()
+    pub fn set_archetype(&mut self, archetype: EnemyArchetype) {
+        self.archetype = archetype;
     }
-}
-
-impl Default for EnemySpawnConfig {
-    fn default() -> Self {
-        Self::new(5.0, 0.1)
-    }
-}
-
-#[derive(Component)]
-pub enum Enemy {
-    Table {
-        last_melee: f32,
-        health_entity: Entity,
-    },
-}
 
-impl Enemy {
     pub fn speed(&self) -> f32 {
-        match self {
-            Table => 25.0,
-        }
+        self.archetype.speed()
     }
 
     pub fn melee_range(&self) -> f32 {
-        match self {
-            Table => 64.0,
-        }
+        self.archetype.melee_range()
     }
 
     pub fn melee_damage(&self) -> i32 {
-        match self {
-            Table => 10,
-        }
+        self.archetype.melee_damage()
     }
 
     pub fn melee_cooldown(&self) -> Duration {
-        match self {
-            Table => Duration::from_secs_f32(0.1),
-        }
+        Duration::from_secs_f32(self.archetype.melee_cooldown())
     }
 
     pub fn last_melee(&self) -> f32 {
-        match self {
-            Enemy::Table { last_melee, .. } => *last_melee,
-        }
+        self.last_melee
     }
 
     pub fn can_melee(&self, time: f32) -> bool {
@@ -103,103 +70,514 @@ impl Enemy {
     }
 
     pub fn set_last_melee(&mut self, time: f32) {
-        match self {
-            Enemy::Table { last_melee, .. } => *last_melee = time,
+        self.last_melee = time;
+    }
+
+    pub fn melee_windup(&self) -> f32 {
+        self.archetype.melee_windup()
+    }
+
+    pub fn melee_recovery(&self) -> f32 {
+        self.archetype.melee_recovery()
+    }
+
+    /// Always false for enemies without a ranged attack.
+    pub fn can_shoot(&self, time: f32) -> bool {
+        self.archetype
+            .ranged()
+            .is_some_and(|ranged| time - self.last_shot > ranged.cooldown())
+    }
+
+    pub fn set_last_shot(&mut self, time: f32) {
+        self.last_shot = time;
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MeleePhase {
+    /// Closing in until the player is within melee range.
+    Approach,
+    /// Rooted and telegraphing, the player can still step out of range.
+    Windup,
+    /// The single tick the blow lands, if the player is still in range.
+    Strike,
+    /// Rooted after the blow, the window to punish the enemy.
+    Recovery,
+    /// Free to move again but can't start another attack until `melee_cooldown` has passed.
+    Cooldown,
+}
+
+/// Where an enemy is in its melee attack. Phase start times are simulation clock seconds.
+#[derive(Component, Debug)]
+pub struct MeleeAttack {
+    phase: MeleePhase,
+    phase_started: f32,
+}
+
+impl Default for MeleeAttack {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MeleeAttack {
+    pub fn new() -> Self {
+        Self {
+            phase: MeleePhase::Approach,
+            phase_started: 0.0,
         }
     }
+
+    pub fn phase(&self) -> MeleePhase {
+        self.phase
+    }
+
+    pub fn phase_started(&self) -> f32 {
+        self.phase_started
+    }
+
+    /// Whether the enemy should stand still while attacking.
+    pub fn is_rooted(&self) -> bool {
+        matches!(
+            self.phase,
+            MeleePhase::Windup | MeleePhase::Strike | MeleePhase::Recovery
+        )
+    }
+
+    fn enter(&mut self, phase: MeleePhase, time: f32) {
+        self.phase = phase;
+        self.phase_started = time;
+    }
 }
 
 #[derive(Bundle)]
 pub struct EnemyBundle {
     collider: Collider,
-    aesprite: AsepriteBundle,
+    transform: TransformBundle,
     enemy: Enemy,
+    melee_attack: MeleeAttack,
     allegence: EnitityAllegence,
     health: Health,
     velocity: Velocity,
 }
 
-fn setup_enemy_plugin(mut commands: Commands) {
-    commands.insert_resource(EnemySpawnConfig::default());
+impl EnemyBundle {
+    pub fn new(archetype: &EnemyArchetype, position: Vec3) -> Self {
+        Self {
+            collider: Collider::circle(archetype.collider_radius()),
+            transform: TransformBundle::from_transform(Transform {
+                scale: Vec3::splat(archetype.scale()),
+                translation: position,
+                ..Default::default()
+            }),
+            enemy: Enemy::new(archetype.clone()),
+            melee_attack: MeleeAttack::new(),
+            allegence: EnitityAllegence::Enemy,
+            health: Health::new(archetype.health()),
+            velocity: Velocity::zero(),
+        }
+    }
 }
 
+/// A dead enemy parked in the [`EnemyPool`]. It's hidden and stripped of everything the
+/// simulation looks at, but keeps its sprite and health bar for whoever spawns next.
+#[derive(Component, Debug)]
+pub struct PooledEnemy;
+
+/// Dead enemies waiting to be brought back, so big waves don't keep allocating new entities and
+/// health bar meshes. Reused last in first out, which keeps replays deterministic.
+#[derive(Resource, Debug, Default)]
+pub struct EnemyPool {
+    entities: Vec<Entity>,
+}
+
+/// Brings an enemy back from the pool if there is one, otherwise spawns a fresh one.
 pub fn spawn_enemy(
+    commands: &mut Commands,
+    pool: &mut EnemyPool,
+    archetype: &EnemyArchetype,
+    position: Vec3,
+) -> Entity {
+    let bundle = EnemyBundle::new(archetype, position);
+
+    match pool.entities.pop() {
+        Some(entity) => {
+            commands
+                .entity(entity)
+                .remove::<PooledEnemy>()
+                .insert((bundle, Visibility::Inherited));
+
+            entity
+        }
+        None => commands.spawn(bundle).id(),
+    }
+}
+
+pub fn pool_dead_enemies(
+    mut commands: Commands,
+    mut pool: ResMut<EnemyPool>,
+    mut entity_died_events: EventReader<EntityDied>,
+    enemy_query: Query<Option<&Boss>, With<Enemy>>,
+) {
+    for event in entity_died_events.iter() {
+        let entity = event.entity();
+
+        let Ok(boss) = enemy_query.get(entity) else {
+            continue;
+        };
+
+        // Bosses never came from the pool, so they don't go back into it
+        if boss.is_some() {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+
+        commands
+            .entity(entity)
+            .remove::<(
+                Enemy,
+                MeleeAttack,
+                Collider,
+                EnitityAllegence,
+                Health,
+                Velocity,
+                InterpolatedPosition,
+            )>()
+            .insert((PooledEnemy, Visibility::Hidden));
+
+        pool.entities.push(entity);
+    }
+}
+
+fn empty_enemy_pool(mut commands: Commands, mut pool: ResMut<EnemyPool>) {
+    for entity in pool.entities.drain(..) {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+/// Set on an enemy once its health bar exists, so a pooled enemy coming back doesn't get a second.
+#[derive(Component)]
+struct EnemyHealthBar;
+
+fn add_enemy_visuals(
     mut commands: Commands,
-    time: Res<Time>,
-    mut spawn_config: ResMut<EnemySpawnConfig>,
     asset_server: Res<AssetServer>,
-    meshes: ResMut<Assets<Mesh>>,
-    materials: ResMut<Assets<ColorMaterial>>,
-    player_query: Query<&Transform, (With<Player>, Without<Enemy>)>,
-    mut rng: ResMut<GlobalEntropy<ChaCha8Rng>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    enemy_query: Query<
+        (
+            Entity,
+            &Enemy,
+            &Transform,
+            Option<&EnemyHealthBar>,
+            Option<&Boss>,
+        ),
+        Added<Enemy>,
+    >,
 ) {
-    spawn_config.tick(time.delta());
+    for (entity, enemy, transform, health_bar, boss) in enemy_query.iter() {
+        commands.entity(entity).insert(AsepriteBundle {
+            aseprite: asset_server.load(enemy.archetype().sprite()),
+            animation: AsepriteAnimation::from(enemy.archetype().idle_tag()),
+            transform: *transform,
+            ..Default::default()
+        });
+
+        // A reused bar is resized by update_healthbar when the fresh Health goes in, and bosses
+        // get a screen-wide bar instead
+        if health_bar.is_some() || boss.is_some() {
+            continue;
+        }
 
-    if !spawn_config.finished() {
-        return;
+        commands.entity(entity).insert(EnemyHealthBar);
+        spawn_health_bar(
+            &mut commands,
+            &mut meshes,
+            &mut materials,
+            HealthBar::new(entity, 24.),
+        );
     }
+}
 
-    if let Ok((player_position)) = player_query.get_single() {
-        let angle = (rng.next_u32() % 360) as f32 * std::f32::consts::PI / 180.0;
-
-        let rotation = Quat::from_rotation_z(angle);
-
-        let position = rotation * Vec3::new(0., 200., 0.) + player_position.translation;
-
-        let entity = commands
-            .spawn(EnemyBundle {
-                collider: Collider::circle(32.),
-                aesprite: AsepriteBundle {
-                    aseprite: asset_server.load(sprites::TableAnim::PATH),
-                    animation: AsepriteAnimation::from(sprites::TableAnim::tags::IDLE),
-                    transform: Transform {
-                        scale: Vec3::splat(2.),
-                        translation: position,
-                        ..Default::default()
-                    },
-                    ..Default::default()
-                },
-                enemy: Enemy::Table {
-                    last_melee: 0.0,
-                    health_entity: Entity::PLACEHOLDER,
-                },
-                allegence: EnitityAllegence::Enemy,
-                health: Health::new(100),
-                velocity: Velocity::zero(),
-            })
-            .id();
+/// Full speed towards the player, easing off to a stop just inside melee range. Ranged enemies
+/// hold at their preferred distance instead, backing off if the player gets too close.
+fn seek_with_arrival(position: Vec3, target: Vec3, enemy: &Enemy) -> Vec3 {
+    let offset = target - position;
+    let distance = offset.length();
+    let arrival_radius = enemy
+        .archetype()
+        .steering()
+        .arrival_radius()
+        .max(f32::EPSILON);
+
+    let (stop_distance, min_slowdown) = match enemy.archetype().ranged() {
+        Some(ranged) => (ranged.preferred_distance(), -1.0),
+        None => (enemy.melee_range() * 0.75, 0.0),
+    };
+
+    let slowdown = ((distance - stop_distance) / arrival_radius).clamp(min_slowdown, 1.0);
+
+    offset.normalize_or_zero() * enemy.speed() * slowdown
+}
+
+/// Pushes away from every neighbour in range, harder the closer they are.
+fn separation(entity: Entity, position: Vec3, neighbours: &[(Entity, Vec3)], radius: f32) -> Vec3 {
+    neighbours
+        .iter()
+        .filter(|(neighbour, _)| *neighbour != entity)
+        .fold(Vec3::ZERO, |push, (neighbour, neighbour_position)| {
+            let away = position - *neighbour_position;
+            let distance = away.length();
+
+            if distance >= radius {
+                return push;
+            }
 
-        spawn_health_bar(commands, meshes, materials, HealthBar::new(entity, 24.));
+            let direction = away
+                .try_normalize()
+                .unwrap_or_else(|| split_direction(entity, *neighbour));
 
-        spawn_config.reset();
+            push + direction * (1.0 - distance / radius)
+        })
+}
+
+/// Enemies on exactly the same spot still need a direction to split in. Each pair gets its own
+/// angle and the two push opposite ways along it, so they don't just move off together.
+fn split_direction(entity: Entity, neighbour: Entity) -> Vec3 {
+    let (first, second) = (entity.min(neighbour), entity.max(neighbour));
+    let hash =
+        (first.to_bits() ^ second.to_bits().rotate_left(32)).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+    let angle = TAU * (hash >> 40) as f32 / (1u64 << 24) as f32;
+    let direction = Vec2::from_angle(angle).extend(0.0);
+
+    if entity == first {
+        direction
+    } else {
+        -direction
     }
 }
 
-pub fn enemy_follow_player(
-    mut player_query: Query<&Transform, (With<Player>, Without<Enemy>)>,
-    mut enemy_query: Query<(&Enemy, &Transform, &mut Velocity)>,
+pub fn steer_enemies(
+    player_query: Query<&Transform, (With<Player>, Without<Enemy>)>,
+    mut enemy_query: Query<(Entity, &Enemy, &MeleeAttack, &Transform, &mut Velocity)>,
 ) {
-    if let Ok(player_transform) = player_query.get_single_mut() {
-        for (enemy, enemy_transform, mut enemy_velocity) in enemy_query.iter_mut() {
-            let direction =
-                (player_transform.translation - enemy_transform.translation).normalize();
-            // *enemy_velocity = Velocity::from_vec(direction * enemy.speed());
+    let Ok(player_transform) = player_query.get_single() else {
+        return;
+    };
+
+    let neighbours: Vec<(Entity, Vec3)> = enemy_query
+        .iter()
+        .map(|(entity, _, _, transform, _)| (entity, transform.translation))
+        .collect();
+
+    for (entity, enemy, melee_attack, enemy_transform, mut enemy_velocity) in enemy_query.iter_mut()
+    {
+        if melee_attack.is_rooted() {
+            *enemy_velocity = Velocity::zero();
+            continue;
         }
+
+        let position = enemy_transform.translation;
+        let weights = enemy.archetype().steering();
+
+        let seek = seek_with_arrival(position, player_transform.translation, enemy);
+        let push =
+            separation(entity, position, &neighbours, weights.separation_radius()) * enemy.speed();
+
+        let steering = seek * weights.seek() + push * weights.separation();
+
+        *enemy_velocity = Velocity::from_vec(steering.clamp_length_max(enemy.speed()));
     }
 }
 
 pub fn enemy_melee_player(
-    player_query: Query<(&Transform, &Collider, Entity), With<Player>>,
-    enemy_query: Query<(&Enemy, &Transform, &Collider, Entity)>,
+    clock: Res<SimulationClock>,
+    player_query: Query<(Entity, &Transform), With<Player>>,
+    mut enemy_query: Query<(Entity, &mut Enemy, &mut MeleeAttack, &Transform), Without<Player>>,
     mut entity_took_damage_events: EventWriter<EntityTookDamage>,
+    mut melee_phase_events: EventWriter<EnemyMeleePhaseChanged>,
 ) {
-    if let Ok((player_transform, player_collider, player_entity)) = player_query.get_single() {
-        for (enemy, enemy_transform, enemy_collider, enemy_entity) in enemy_query.iter() {
-            if enemy_collider.is_colliding(enemy_transform, player_collider, player_transform) {
+    let now = clock.elapsed_seconds();
+    let player = player_query.get_single().ok();
+
+    for (entity, mut enemy, mut melee_attack, enemy_transform) in enemy_query.iter_mut() {
+        let player_in_range = player.is_some_and(|(_, player_transform)| {
+            enemy_transform
+                .translation
+                .distance(player_transform.translation)
+                <= enemy.melee_range()
+        });
+        let phase_elapsed = now - melee_attack.phase_started();
+
+        let next_phase = match melee_attack.phase() {
+            MeleePhase::Approach if player_in_range => MeleePhase::Windup,
+            MeleePhase::Windup if phase_elapsed >= enemy.melee_windup() => MeleePhase::Strike,
+            MeleePhase::Strike => MeleePhase::Recovery,
+            MeleePhase::Recovery if phase_elapsed >= enemy.melee_recovery() => MeleePhase::Cooldown,
+            MeleePhase::Cooldown if enemy.can_melee(now) => MeleePhase::Approach,
+            _ => continue,
+        };
+
+        if next_phase == MeleePhase::Strike {
+            enemy.set_last_melee(now);
+
+            if let Some((player_entity, _)) = player.filter(|_| player_in_range) {
                 entity_took_damage_events
                     .send(EntityTookDamage::new(player_entity, enemy.melee_damage()));
             }
         }
+
+        melee_attack.enter(next_phase, now);
+        melee_phase_events.send(EnemyMeleePhaseChanged::new(entity, next_phase));
+    }
+}
+
+/// Where to aim so a projectile meets a target moving at a constant velocity, scaled back towards
+/// the target's current position by `lead`.
+fn lead_target(
+    shooter: Vec3,
+    target: Vec3,
+    target_velocity: Vec3,
+    projectile_speed: f32,
+    lead: f32,
+) -> Vec3 {
+    let offset = target - shooter;
+
+    // Solve |offset + velocity * t| = speed * t for the earliest positive t
+    let a = target_velocity.length_squared() - projectile_speed.powi(2);
+    let b = 2.0 * offset.dot(target_velocity);
+    let c = offset.length_squared();
+
+    let time_to_hit = if a.abs() < f32::EPSILON {
+        (b.abs() > f32::EPSILON).then(|| -c / b)
+    } else {
+        let discriminant = b * b - 4.0 * a * c;
+
+        (discriminant >= 0.0).then(|| {
+            let root = discriminant.sqrt();
+            let t1 = (-b - root) / (2.0 * a);
+            let t2 = (-b + root) / (2.0 * a);
+
+            if t1 > 0.0 && (t1 < t2 || t2 <= 0.0) {
+                t1
+            } else {
+                t2
+            }
+        })
+    };
+
+    match time_to_hit.filter(|t| *t > 0.0) {
+        Some(t) => target + target_velocity * t * lead,
+        None => target,
+    }
+}
+
+pub fn enemy_fire_at_player(
+    mut commands: Commands,
+    clock: Res<SimulationClock>,
+    player_query: Query<(&Transform, Option<&Velocity>), With<Player>>,
+    mut enemy_query: Query<(Entity, &mut Enemy, &MeleeAttack, &Transform), Without<Player>>,
+    blocker_query: Query<(Entity, &Collider, &Transform), (With<Enemy>, Without<Player>)>,
+) {
+    let Ok((player_transform, player_velocity)) = player_query.get_single() else {
+        return;
+    };
+
+    let now = clock.elapsed_seconds();
+    let player_position = player_transform.translation;
+    let player_velocity = player_velocity.map_or(Vec3::ZERO, |velocity| velocity.as_vec());
+
+    for (entity, mut enemy, melee_attack, enemy_transform) in enemy_query.iter_mut() {
+        if melee_attack.is_rooted() || !enemy.can_shoot(now) {
+            continue;
+        }
+
+        let Some(ranged) = enemy.archetype().ranged().copied() else {
+            continue;
+        };
+
+        let position = enemy_transform.translation;
+        let aim = lead_target(
+            position,
+            player_position,
+            player_velocity,
+            ranged.projectile_speed(),
+            ranged.lead(),
+        );
+
+        let Some(direction) = (aim - position).try_normalize() else {
+            continue;
+        };
+
+        // Volleys fan out evenly across the spread, centred on the aim
+        let volley = ranged.volley();
+        let shots = (0..volley).map(|index| {
+            let angle = if volley > 1 {
+                ranged.spread() * (index as f32 / (volley - 1) as f32 - 0.5)
+            } else {
+                0.0
+            };
+
+            Quat::from_rotation_z(angle) * direction
+        });
+
+        // Don't shoot through other enemies, each shot needs a clear line for as far as it flies.
+        // With none clear the enemy holds fire until steering opens one up.
+        let clear_shots: Vec<Vec3> = shots
+            .filter(|shot| {
+                let end = position + *shot * ranged.range();
+
+                !blocker_query.iter().any(|(blocker, collider, transform)| {
+                    blocker != entity
+                        && collider.intersects_segment(
+                            transform,
+                            position,
+                            end,
+                            ranged.projectile_radius(),
+                        )
+                })
+            })
+            .collect();
+
+        if clear_shots.is_empty() {
+            continue;
+        }
+
+        for shot in clear_shots {
+            commands.spawn(ProjectileBundle {
+                transform: Transform::from_translation(position),
+                velocity: Velocity::from_vec(shot * ranged.projectile_speed()),
+                collider: Collider::circle(ranged.projectile_radius()),
+                projectile: Projectile::new(ranged.projectile_damage()),
+                allegence: EnitityAllegence::Enemy,
+                range: ProjectileRange::new(position, ranged.range()),
+            });
+        }
+
+        enemy.set_last_shot(now);
+    }
+}
+
+/// A ring closing in on the enemy while it winds up, so the player can see the blow coming.
+fn render_melee_telegraphs(
+    mut gizmos: Gizmos,
+    clock: Res<SimulationClock>,
+    enemy_query: Query<(&Enemy, &MeleeAttack, &Transform)>,
+) {
+    for (enemy, melee_attack, transform) in enemy_query.iter() {
+        if melee_attack.phase() != MeleePhase::Windup {
+            continue;
+        }
+
+        let progress = ((clock.elapsed_seconds() - melee_attack.phase_started())
+            / enemy.melee_windup().max(f32::EPSILON))
+        .clamp(0.0, 1.0);
+
+        gizmos.circle_2d(
+            transform.translation.truncate(),
+            enemy.melee_range() * (2.0 - progress),
+            Color::rgba(1.0, 0.2, 0.1, 0.25 + 0.75 * progress),
+        );
     }
 }
 
@@ -207,15 +585,34 @@ pub struct EnemyPlugin;
 
 impl Plugin for EnemyPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Startup, (setup_enemy_plugin));
+        app.init_resource::<EnemyArchetypes>();
+        app.init_resource::<EnemyPool>();
+        app.add_systems(
+            OnEnter(AppState::GameOver),
+            (despawn_all::<Enemy>, empty_enemy_pool),
+        );
+        app.add_systems(FixedUpdate, (steer_enemies).in_set(GameSet::Ai));
+        app.add_systems(
+            FixedUpdate,
+            (enemy_melee_player, enemy_fire_at_player)
+                .chain()
+                .after(projectile_hurt_entity)
+                .in_set(GameSet::DealDamage),
+        );
         app.add_systems(
-            Update,
-            (
-                enemy_follow_player,
-                spawn_enemy.run_if(resource_exists::<EnemySpawnConfig>()),
-            )
-                .in_set(GameSet::Ai),
+            FixedUpdate,
+            (pool_dead_enemies)
+                .after(take_damage)
+                .in_set(GameSet::ResolveDamage),
         );
-        app.add_systems(Update, (enemy_melee_player).in_set(GameSet::DealDamage));
+    }
+}
+
+pub struct EnemyVisualsPlugin;
+
+impl Plugin for EnemyVisualsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, (add_enemy_visuals).in_set(GameSet::Animation));
+        app.add_systems(Update, (render_melee_telegraphs).in_set(GameSet::Ui));
     }
 }
@@ -0,0 +1,652 @@
+use std::time::Duration;
+
+use bevy::{ecs::event::ManualEventReader, prelude::*, time::TimeUpdateStrategy};
+use bevy_prng::ChaCha8Rng;
+use bevy_rand::prelude::EntropyPlugin;
+
+use crate::{app::AppState, settings::Settings};
+
+use super::{
+    archetype::{EnemyArchetype, EnemyArchetypes},
+    boss::Boss,
+    enemy::{Enemy, EnemyBundle},
+    health::Health,
+    intent::PlayerIntent,
+    physics::{InterpolatedPosition, Velocity},
+    pickup::Pickup,
+    player::Player,
+    projectile::Projectile,
+    rng::SeedOverride,
+    spawn::{SpawnArea, VIEW_SIZE},
+    wave::{WaveDefinition, WaveDirector, WaveSchedule},
+    EntityTookDamage, GameSimulationPlugin, WaveCleared, WaveStarted,
+};
+
+/// The simulation on `MinimalPlugins` with no window, renderer or assets. Time only moves when
+/// `advance` is called, one simulation tick per call to `App::update`, so runs are exact.
+pub struct HeadlessGame {
+    app: App,
+}
+
+impl HeadlessGame {
+    /// Starts a run with the given seed, the player is spawned and no ticks have run yet.
+    pub fn new(seed: u64) -> Self {
+        let mut app = App::new();
+
+        app.add_plugins((MinimalPlugins, EntropyPlugin::<ChaCha8Rng>::default()));
+        app.add_state::<AppState>();
+        app.insert_resource(Settings::default());
+        let archetypes = [
+            include_str!("../../assets/enemies/table.enemy.ron"),
+            include_str!("../../assets/enemies/ranged_table.enemy.ron"),
+            include_str!("../../assets/enemies/boss_table.enemy.ron"),
+        ]
+        .map(|ron| EnemyArchetype::from_ron(ron).expect("archetype should parse"));
+        app.insert_resource(EnemyArchetypes::new(archetypes));
+        app.insert_resource(
+            WaveSchedule::from_ron(include_str!("../../assets/run.waves.ron"))
+                .expect("wave schedule should parse"),
+        );
+        app.insert_resource(TimeUpdateStrategy::ManualDuration(Duration::ZERO));
+        app.add_plugins(GameSimulationPlugin);
+
+        app.world.resource_mut::<SeedOverride>().set(seed);
+        app.world
+            .resource_mut::<NextState<AppState>>()
+            .set(AppState::InGame);
+
+        // Applies the transition into the run, the first frame never has a time delta
+        app.update();
+
+        let period = app.world.resource::<FixedTime>().period;
+        app.insert_resource(TimeUpdateStrategy::ManualDuration(period));
+
+        Self { app }
+    }
+
+    pub fn app_mut(&mut self) -> &mut App {
+        &mut self.app
+    }
+
+    /// Replaces the player's intent, it stays in effect until set again.
+    pub fn set_intent(&mut self, intent: PlayerIntent) {
+        *self.app.world.resource_mut::<PlayerIntent>() = intent;
+    }
+
+    fn archetype(&self, id: &str) -> EnemyArchetype {
+        self.app
+            .world
+            .resource::<EnemyArchetypes>()
+            .get(id)
+            .expect("archetype should exist")
+            .clone()
+    }
+
+    /// Spawns an enemy outside of the usual spawn schedule.
+    pub fn spawn_enemy_at(&mut self, archetype: &str, position: Vec3) -> Entity {
+        let archetype = self.archetype(archetype);
+
+        self.app
+            .world
+            .spawn(EnemyBundle::new(&archetype, position))
+            .id()
+    }
+
+    /// Spawns a boss outside of the usual schedule, without the arrival announcement.
+    pub fn spawn_boss_at(&mut self, archetype: &str, position: Vec3) -> Entity {
+        let archetype = self.archetype(archetype);
+
+        self.app
+            .world
+            .spawn((EnemyBundle::new(&archetype, position), Boss::new()))
+            .id()
+    }
+
+    /// Stands in for the tilemap, which sets the walkable area when the game has a renderer.
+    pub fn set_walkable(&mut self, walkable: Rect) {
+        self.app
+            .world
+            .resource_mut::<SpawnArea>()
+            .set_walkable(Some(walkable));
+    }
+
+    pub fn spawn_area(&self) -> SpawnArea {
+        *self.app.world.resource::<SpawnArea>()
+    }
+
+    /// Puts an entity somewhere else as if it had always been there, nothing in between.
+    pub fn teleport(&mut self, entity: Entity, position: Vec3) {
+        let mut entity = self.app.world.entity_mut(entity);
+
+        entity.get_mut::<Transform>().unwrap().translation = position;
+        entity.insert(InterpolatedPosition::new(position));
+    }
+
+    pub fn wave_schedule(&self) -> &WaveSchedule {
+        self.app.world.resource::<WaveSchedule>()
+    }
+
+    /// The wave in progress or last cleared, `None` before the run's first tick.
+    pub fn wave(&self) -> Option<u32> {
+        self.app
+            .world
+            .get_resource::<WaveDirector>()
+            .map(WaveDirector::wave)
+    }
+
+    /// Simulation ticks in that many seconds, rounded to the nearest whole tick.
+    pub fn ticks(&self, seconds: f32) -> u32 {
+        let period = self.app.world.resource::<FixedTime>().period;
+
+        (seconds / period.as_secs_f32()).round() as u32
+    }
+
+    pub fn damage(&mut self, entity: Entity, damage: i32) {
+        self.app
+            .world
+            .send_event(EntityTookDamage::new(entity, damage));
+    }
+
+    /// Deals every living enemy more damage than it has health.
+    pub fn kill_enemies(&mut self) {
+        for enemy in self.enemies() {
+            self.damage(enemy, i32::MAX);
+        }
+    }
+
+    pub fn advance(&mut self, ticks: u32) {
+        for _ in 0..ticks {
+            self.app.update();
+        }
+    }
+
+    pub fn state(&self) -> AppState {
+        self.app.world.resource::<State<AppState>>().get().clone()
+    }
+
+    pub fn player_health(&mut self) -> Option<i32> {
+        self.app
+            .world
+            .query_filtered::<&Health, With<Player>>()
+            .get_single(&self.app.world)
+            .ok()
+            .map(|health| health.health())
+    }
+
+    pub fn player_position(&mut self) -> Option<Vec3> {
+        self.app
+            .world
+            .query_filtered::<&Transform, With<Player>>()
+            .get_single(&self.app.world)
+            .ok()
+            .map(|transform| transform.translation)
+    }
+
+    pub fn enemy_count(&mut self) -> usize {
+        self.enemy_positions().len()
+    }
+
+    pub fn enemies(&mut self) -> Vec<Entity> {
+        self.app
+            .world
+            .query_filtered::<Entity, With<Enemy>>()
+            .iter(&self.app.world)
+            .collect()
+    }
+
+    /// `None` once the boss is dead.
+    pub fn boss_phase(&self, entity: Entity) -> Option<usize> {
+        self.app.world.get::<Boss>(entity).map(|boss| boss.phase())
+    }
+
+    pub fn pickup_count(&mut self) -> usize {
+        self.app
+            .world
+            .query_filtered::<(), With<Pickup>>()
+            .iter(&self.app.world)
+            .count()
+    }
+
+    /// Position and velocity of every projectile in flight.
+    pub fn projectiles(&mut self) -> Vec<(Vec3, Vec3)> {
+        self.app
+            .world
+            .query_filtered::<(&Transform, &Velocity), With<Projectile>>()
+            .iter(&self.app.world)
+            .map(|(transform, velocity)| (transform.translation, velocity.as_vec()))
+            .collect()
+    }
+
+    /// Runs until the first projectile is in flight, `false` if none was fired in time.
+    pub fn advance_until_fired(&mut self, max_ticks: u32) -> bool {
+        for _ in 0..max_ticks {
+            self.app.update();
+
+            if !self.projectiles().is_empty() {
+                return true;
+            }
+        }
+
+        false
+    }
+
+    pub fn enemy_positions(&mut self) -> Vec<Vec3> {
+        self.app
+            .world
+            .query_filtered::<&Transform, With<Enemy>>()
+            .iter(&self.app.world)
+            .map(|transform| transform.translation)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn player_moves_at_speed_for_one_second() {
+        let mut game = HeadlessGame::new(1);
+
+        game.set_intent(PlayerIntent::new(Vec3::X, None, false, false));
+        game.advance(60);
+
+        let position = game.player_position().unwrap();
+        assert!((position.x - 200.).abs() < 0.1, "{position}");
+        assert_eq!(position.y, 0.);
+    }
+
+    #[test]
+    fn first_wave_starts_after_opening_rest() {
+        let mut game = HeadlessGame::new(1);
+
+        game.advance(290);
+        assert_eq!(game.enemy_count(), 0);
+
+        game.advance(20);
+        assert_eq!(game.enemy_count(), 1);
+        assert_eq!(game.player_health(), Some(100));
+    }
+
+    #[test]
+    fn waves_start_and_clear_with_rest_in_between() {
+        let mut game = HeadlessGame::new(1);
+
+        let schedule = game.wave_schedule().clone();
+        let first_wave = schedule.wave(1).unwrap();
+        let last_spawn = first_wave
+            .groups()
+            .iter()
+            .map(|group| group.delay() + group.interval() * (group.count() - 1) as f32)
+            .fold(0.0, f32::max);
+
+        let mut started = ManualEventReader::<WaveStarted>::default();
+        let mut cleared = ManualEventReader::<WaveCleared>::default();
+        let mut log = vec![];
+
+        // Enemies die as soon as they turn up, so each wave clears right after its last spawn
+        for tick in 1..=game.ticks(25.0) {
+            game.kill_enemies();
+            game.advance(1);
+
+            let world = &game.app_mut().world;
+            for event in started.iter(world.resource::<Events<WaveStarted>>()) {
+                log.push(("started", event.wave(), tick));
+            }
+            for event in cleared.iter(world.resource::<Events<WaveCleared>>()) {
+                log.push(("cleared", event.wave(), tick));
+            }
+        }
+
+        let order: Vec<(&str, u32)> = log.iter().map(|(kind, wave, _)| (*kind, *wave)).collect();
+        assert_eq!(order, [("started", 1), ("cleared", 1), ("started", 2)]);
+
+        let [first_started, first_cleared, second_started] = [log[0].2, log[1].2, log[2].2];
+
+        assert!(first_started.abs_diff(game.ticks(schedule.opening_rest())) <= 1);
+        assert!((first_cleared - first_started).abs_diff(game.ticks(last_spawn)) <= 3);
+        assert!((second_started - first_cleared).abs_diff(game.ticks(first_wave.rest())) <= 1);
+    }
+
+    #[test]
+    fn waves_past_the_end_of_the_schedule_keep_growing() {
+        let game = HeadlessGame::new(1);
+
+        let counts = |wave: WaveDefinition| -> Vec<u32> {
+            wave.groups().iter().map(|group| group.count()).collect()
+        };
+
+        // The last listed wave has 10 and 4, each repeat is a quarter bigger than the one before
+        let schedule = game.wave_schedule();
+        assert_eq!(counts(schedule.wave(3).unwrap()), [10, 4]);
+        assert_eq!(counts(schedule.wave(4).unwrap()), [13, 5]);
+        assert_eq!(counts(schedule.wave(5).unwrap()), [16, 6]);
+    }
+
+    #[test]
+    fn enemies_spawn_on_the_map_when_zoomed_out_past_its_edges() {
+        let mut game = HeadlessGame::new(1);
+
+        // Zoomed out the view is bigger than the map both ways, so just off screen is off the
+        // map too
+        let map = Rect::from_center_size(Vec2::ZERO, Vec2::splat(1024.));
+        game.set_walkable(map);
+
+        game.advance(game.ticks(game.wave_schedule().opening_rest()) + 10);
+
+        let table = game.archetype("table");
+        let positions = game.enemy_positions();
+        assert_eq!(positions.len(), 1);
+        assert!(map
+            .inset(-table.collider_radius())
+            .contains(positions[0].truncate()));
+
+        let distance = positions[0].distance(game.player_position().unwrap());
+        assert!(
+            distance >= table.spawn().min_player_distance(),
+            "{distance}"
+        );
+    }
+
+    #[test]
+    fn enemies_spawn_outside_the_view_at_full_zoom() {
+        let mut game = HeadlessGame::new(1);
+
+        let zoom = Settings::default().camera_zoom_limits().max();
+        assert!(zoom > 1.0);
+
+        let mut spawned = Vec::new();
+        for _ in 0..game.ticks(game.wave_schedule().opening_rest() + 10.) {
+            game.advance(1);
+
+            let player = game.player_position().unwrap().truncate();
+            let view = Rect::from_center_half_size(player, VIEW_SIZE / 2. * zoom);
+
+            for enemy in game.enemies() {
+                if spawned.contains(&enemy) {
+                    continue;
+                }
+
+                let position = game
+                    .app_mut()
+                    .world
+                    .get::<Transform>(enemy)
+                    .unwrap()
+                    .translation;
+                assert!(!view.contains(position.truncate()), "{position}");
+                spawned.push(enemy);
+            }
+        }
+
+        assert!(spawned.len() >= 3);
+    }
+
+    #[test]
+    fn melee_enemy_winds_up_before_each_strike() {
+        let mut game = HeadlessGame::new(1);
+
+        game.spawn_enemy_at("table", Vec3::new(50., 0., 1.));
+
+        // Still winding up
+        game.advance(20);
+        assert_eq!(game.player_health(), Some(100));
+
+        // One strike, then recovery and cooldown before the next windup can land
+        game.advance(40);
+        assert_eq!(game.player_health(), Some(90));
+    }
+
+    #[test]
+    fn ranged_enemy_leads_a_moving_player() {
+        let mut game = HeadlessGame::new(1);
+
+        game.spawn_enemy_at("ranged_table", Vec3::new(0., 240., 1.));
+        game.set_intent(PlayerIntent::new(Vec3::X, None, false, false));
+
+        assert!(game.advance_until_fired(120));
+
+        let (position, velocity) = game.projectiles()[0];
+        let straight_at_player = (game.player_position().unwrap() - position).normalize();
+        let heading = velocity.normalize();
+
+        // Aimed ahead of the player in the direction they're running
+        assert!(
+            heading.x > straight_at_player.x + 0.1,
+            "{heading} {straight_at_player}"
+        );
+    }
+
+    #[test]
+    fn ranged_volley_fans_out_across_spread() {
+        let mut game = HeadlessGame::new(1);
+
+        let archetype = EnemyArchetype::from_ron(
+            r#"(
+                id: "volley_table",
+                sprite: "table.aseprite",
+                idle_tag: "idle",
+                speed: 0.0,
+                health: 60,
+                collider_radius: 24.0,
+                melee_damage: 5,
+                melee_range: 48.0,
+                melee_cooldown: 0.5,
+                scale: 1.5,
+                ranged: Some((
+                    preferred_distance: 240.0,
+                    cooldown: 1.5,
+                    projectile_speed: 300.0,
+                    projectile_damage: 8,
+                    projectile_radius: 8.0,
+                    volley: 3,
+                    spread: 0.6,
+                )),
+            )"#,
+        )
+        .unwrap();
+        game.app_mut()
+            .world
+            .spawn(EnemyBundle::new(&archetype, Vec3::new(0., 240., 1.)));
+
+        assert!(game.advance_until_fired(120));
+
+        let mut angles: Vec<f32> = game
+            .projectiles()
+            .iter()
+            .map(|(_, velocity)| velocity.angle_between(Vec3::NEG_Y))
+            .collect();
+        angles.sort_by(f32::total_cmp);
+
+        // One shot straight down at the player and one either side, half the spread off
+        assert_eq!(angles.len(), 3);
+        assert!(angles[0] < 0.01, "{angles:?}");
+        assert!((angles[1] - 0.3).abs() < 0.01, "{angles:?}");
+        assert!((angles[2] - 0.3).abs() < 0.01, "{angles:?}");
+    }
+
+    #[test]
+    fn ranged_enemy_holds_fire_with_enemy_in_the_way() {
+        let mut game = HeadlessGame::new(1);
+
+        game.spawn_enemy_at("ranged_table", Vec3::new(0., 240., 1.));
+        game.spawn_enemy_at("table", Vec3::new(0., 120., 1.));
+
+        assert!(!game.advance_until_fired(180));
+    }
+
+    #[test]
+    fn projectiles_are_gone_past_their_range() {
+        let mut game = HeadlessGame::new(1);
+
+        let enemy = game.spawn_enemy_at("ranged_table", Vec3::new(0., 240., 1.));
+        game.set_intent(PlayerIntent::new(Vec3::X, None, false, false));
+        assert!(game.advance_until_fired(120));
+
+        // The player doubles back so the shot aimed ahead of them misses
+        game.set_intent(PlayerIntent::new(Vec3::NEG_X, None, false, false));
+        game.damage(enemy, 1000);
+
+        // Range over speed is a little over two seconds
+        game.advance(100);
+        assert_eq!(game.projectiles().len(), 1);
+
+        game.advance(40);
+        assert!(game.projectiles().is_empty());
+    }
+
+    #[test]
+    fn same_seed_spawns_enemies_in_same_place() {
+        let mut first = HeadlessGame::new(42);
+        let mut second = HeadlessGame::new(42);
+
+        first.advance(600);
+        second.advance(600);
+
+        assert!(first.enemy_count() > 0);
+        assert_eq!(first.enemy_positions(), second.enemy_positions());
+    }
+
+    #[test]
+    fn dead_enemies_are_reused_by_the_next_spawn() {
+        let mut game = HeadlessGame::new(1);
+
+        let enemy = game.spawn_enemy_at("table", Vec3::new(500., 0., 1.));
+        game.damage(enemy, 1000);
+        game.advance(1);
+        assert_eq!(game.enemy_count(), 0);
+
+        // The first wave's first enemy is due once the opening rest is over
+        game.advance(game.ticks(game.wave_schedule().opening_rest()) + 10);
+        assert_eq!(game.enemies(), vec![enemy]);
+    }
+
+    #[test]
+    fn spawning_pauses_at_the_population_cap() {
+        let mut game = HeadlessGame::new(1);
+
+        // A row along the top, slow enough not to reach the player during the test
+        let cap = game.wave_schedule().population().max_enemies();
+        let crowd: Vec<Entity> = (0..cap)
+            .map(|index| {
+                let x = index as f32 * 20. - cap as f32 * 10.;
+                game.spawn_enemy_at("table", Vec3::new(x, 400., 1.))
+            })
+            .collect();
+
+        game.advance(game.ticks(game.wave_schedule().opening_rest()) + 10);
+        assert_eq!(game.enemy_count(), cap as usize);
+
+        // The first wave's overdue enemy takes the place, and the entity, of the one that died
+        game.damage(crowd[0], 1000);
+        game.advance(10);
+        assert_eq!(game.enemy_count(), cap as usize);
+        assert!(game.enemies().contains(&crowd[0]));
+    }
+
+    #[test]
+    fn enemies_left_far_behind_are_brought_back_off_screen() {
+        let mut game = HeadlessGame::new(1);
+
+        // The run's first tick starts the wave director and gives the enemy a rendered position
+        let enemy = game.spawn_enemy_at("table", Vec3::new(300., 0., 1.));
+        game.advance(1);
+
+        game.teleport(enemy, Vec3::new(3000., 0., 1.));
+        game.advance(1);
+
+        let view = game
+            .spawn_area()
+            .view(game.player_position().unwrap().truncate());
+        let margin = game.wave_schedule().population().relocate_margin();
+        let position = game.enemy_positions()[0].truncate();
+
+        assert_eq!(game.enemies(), vec![enemy]);
+        assert!(!view.contains(position), "{position}");
+        assert!(view.inset(margin).contains(position), "{position}");
+
+        // Rendering blends from where it was brought back to, not from 3000 away
+        let interpolated = game
+            .app_mut()
+            .world
+            .get::<InterpolatedPosition>(enemy)
+            .unwrap();
+        let streak = interpolated.previous().distance(interpolated.current());
+        assert!(streak < 10., "{streak}");
+    }
+
+    #[test]
+    fn enemies_on_screen_at_full_zoom_are_left_where_they_are() {
+        let mut game = HeadlessGame::new(1);
+
+        let zoom = Settings::default().camera_zoom_limits().max();
+        let enemy = game.spawn_enemy_at("table", Vec3::new(VIEW_SIZE.x / 2. * zoom - 40., 0., 1.));
+        game.advance(2);
+
+        let position = game.enemy_positions()[0];
+        assert_eq!(game.enemies(), vec![enemy]);
+        assert!(position.x > VIEW_SIZE.x / 2. * zoom - 50., "{position}");
+    }
+
+    #[test]
+    fn boss_changes_phase_at_thresholds_and_drops_rewards() {
+        let mut game = HeadlessGame::new(1);
+
+        let boss = game.spawn_boss_at("boss_table", Vec3::new(600., 0., 1.));
+
+        game.damage(boss, 600);
+        game.advance(1);
+        assert_eq!(game.boss_phase(boss), Some(1));
+
+        game.damage(boss, 450);
+        game.advance(1);
+        assert_eq!(game.boss_phase(boss), Some(2));
+
+        game.damage(boss, 500);
+        game.advance(1);
+        assert_eq!(game.boss_phase(boss), None);
+        assert_eq!(game.pickup_count(), 2);
+    }
+
+    #[test]
+    fn boss_arrives_with_its_wave_on_a_map_too_small_for_its_spawn_rules() {
+        let mut game = HeadlessGame::new(1);
+
+        // Nowhere on the map is off screen and far enough from the player for the boss
+        let map = Rect::from_center_size(Vec2::ZERO, Vec2::splat(640.));
+        game.set_walkable(map);
+
+        // Everything else dies as soon as it turns up, so the run gets to the boss's wave quickly
+        let mut boss = None;
+        for _ in 0..game.ticks(60.) {
+            for enemy in game.enemies() {
+                if game.boss_phase(enemy).is_none() {
+                    game.damage(enemy, i32::MAX);
+                }
+            }
+
+            game.advance(1);
+
+            boss = game
+                .enemies()
+                .into_iter()
+                .find(|enemy| game.boss_phase(*enemy).is_some());
+            if boss.is_some() {
+                break;
+            }
+        }
+
+        let boss = boss.expect("boss should have arrived");
+        assert_eq!(game.wave(), Some(3));
+
+        let boss_table = game.archetype("boss_table");
+        let position = game
+            .app_mut()
+            .world
+            .get::<Transform>(boss)
+            .unwrap()
+            .translation;
+        assert!(map
+            .inset(-boss_table.collider_radius())
+            .contains(position.truncate()));
+    }
+}
@@ -4,7 +4,9 @@ use bevy::{
     transform::commands,
 };
 
-use super::{GameSet, EntityTookDamage};
+use crate::app::AppState;
+
+use super::{enemy::Enemy, player::Player, EntityDied, EntityTookDamage, GameSet};
 
 #[derive(Component, Debug)]
 pub struct Health {
@@ -21,6 +23,29 @@ impl Health {
         self.health -= damage;
     }
 
+    /// Never above max.
+    pub fn heal(&mut self, amount: i32) {
+        self.health = (self.health + amount).min(self.max);
+    }
+
+    /// The extra max comes with as much extra health.
+    pub fn raise_max(&mut self, amount: i32) {
+        self.max += amount;
+        self.health += amount;
+    }
+
+    /// Keeps the same share of health, a living entity is never left at 0.
+    pub fn set_max(&mut self, max: i32) {
+        let health = (self.health_percentage() * max as f32).round() as i32;
+
+        self.health = if self.is_dead() {
+            health
+        } else {
+            health.max(1)
+        };
+        self.max = max;
+    }
+
     pub fn health(&self) -> i32 {
         self.health
     }
@@ -68,9 +93,9 @@ struct HealthBarBundle<M: Material2d> {
 }
 
 pub fn spawn_health_bar(
-    mut commands: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<ColorMaterial>>,
+    commands: &mut Commands,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<ColorMaterial>,
     health_bar: HealthBar,
 ) -> Entity {
     let parent = health_bar.health_entity();
@@ -115,15 +140,37 @@ pub fn update_healthbar(
 
 pub fn take_damage(
     mut commands: Commands,
-    mut entity_query: Query<(Entity, &mut Health)>,
+    mut entity_query: Query<(
+        Entity,
+        &mut Health,
+        &Transform,
+        Option<&Player>,
+        Option<&Enemy>,
+    )>,
     mut entity_took_damage_events: EventReader<EntityTookDamage>,
+    mut entity_died_events: EventWriter<EntityDied>,
+    mut next_state: ResMut<NextState<AppState>>,
 ) {
     for event in entity_took_damage_events.iter() {
-        if let Ok((entity, mut health)) = entity_query.get_mut(event.entity) {
+        if let Ok((entity, mut health, transform, player, enemy)) =
+            entity_query.get_mut(event.entity)
+        {
+            if health.is_dead() {
+                continue;
+            }
+
             health.damage(event.damage);
 
             if health.is_dead() {
-                commands.entity(entity).despawn_recursive();
+                if player.is_some() {
+                    next_state.set(AppState::GameOver);
+                }
+                entity_died_events.send(EntityDied::new(entity, transform.translation));
+
+                // Dead enemies go back to the pool instead
+                if enemy.is_none() {
+                    commands.entity(entity).despawn_recursive();
+                }
             }
         }
     }
@@ -132,8 +179,15 @@ pub fn take_damage(
 pub struct HealthPlugin;
 
 impl Plugin for HealthPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(FixedUpdate, (take_damage).in_set(GameSet::ResolveDamage));
+    }
+}
+
+pub struct HealthBarPlugin;
+
+impl Plugin for HealthBarPlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(Update, (update_healthbar).in_set(GameSet::Ui));
-        app.add_systems(Update, (take_damage).in_set(GameSet::ResolveDamage));
     }
 }
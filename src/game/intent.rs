@@ -0,0 +1,104 @@
+use bevy::prelude::*;
+
+use crate::input::{ActiveInputDevice, GamepadSticks, InputAction, InputDevice};
+
+use super::{
+    camera::{CursorWorldPosition, CAMERA_OFFSET_FROM_PLAYER},
+    player::Player,
+};
+
+/// What the player wants to do this tick, independent of which device it came from. Gameplay
+/// only ever reads this, so live input and replays drive the simulation identically.
+#[derive(Resource, Debug, Clone, Copy, Default, PartialEq)]
+pub struct PlayerIntent {
+    movement: Vec3,
+    aim: Option<Vec3>,
+    fire_primary: bool,
+    fire_secondary: bool,
+}
+
+impl PlayerIntent {
+    pub fn new(
+        movement: Vec3,
+        aim: Option<Vec3>,
+        fire_primary: bool,
+        fire_secondary: bool,
+    ) -> Self {
+        Self {
+            movement,
+            aim,
+            fire_primary,
+            fire_secondary,
+        }
+    }
+
+    /// Movement direction with a length of at most one.
+    pub fn movement(&self) -> Vec3 {
+        self.movement
+    }
+
+    /// Where the player is aiming relative to themselves, clamped to the leading camera offset.
+    pub fn aim(&self) -> Option<Vec3> {
+        self.aim
+    }
+
+    /// Twin-stick players who aren't aiming still expect to throw where they're running.
+    pub fn facing(&self) -> Option<Vec3> {
+        self.aim.unwrap_or(self.movement).try_normalize()
+    }
+
+    pub fn fire_primary(&self) -> bool {
+        self.fire_primary
+    }
+
+    pub fn fire_secondary(&self) -> bool {
+        self.fire_secondary
+    }
+}
+
+pub fn gather_player_intent(
+    cursor: Res<CursorWorldPosition>,
+    player_query: Query<&Transform, With<Player>>,
+    actions: Res<Input<InputAction>>,
+    active_device: Res<ActiveInputDevice>,
+    sticks: Res<GamepadSticks>,
+    mut intent: ResMut<PlayerIntent>,
+) {
+    let mut movement = Vec3::ZERO;
+    if actions.pressed(InputAction::MoveLeft) {
+        movement.x -= 1.0;
+    }
+    if actions.pressed(InputAction::MoveRight) {
+        movement.x += 1.0;
+    }
+    if actions.pressed(InputAction::MoveDown) {
+        movement.y -= 1.0;
+    }
+    if actions.pressed(InputAction::MoveUp) {
+        movement.y += 1.0;
+    }
+    movement = if movement == Vec3::ZERO {
+        sticks.movement().extend(0.0).clamp_length_max(1.0)
+    } else {
+        movement.normalize_or_zero()
+    };
+
+    let aim = match active_device.get() {
+        InputDevice::KeyboardMouse => cursor.get().zip(player_query.get_single().ok()).map(
+            |(cursor_position, player_transform)| {
+                (cursor_position - player_transform.translation.truncate())
+                    .extend(0.0)
+                    .clamp_length_max(CAMERA_OFFSET_FROM_PLAYER)
+            },
+        ),
+        InputDevice::Gamepad(_) => Some(sticks.aim().extend(0.0) * CAMERA_OFFSET_FROM_PLAYER)
+            .filter(|offset| *offset != Vec3::ZERO),
+    };
+
+    *intent = PlayerIntent::new(
+        movement,
+        aim,
+        actions.pressed(InputAction::FirePrimary),
+        actions.pressed(InputAction::FireSecondary),
+    );
+}
@@ -1,7 +1,6 @@
-use bevy::{
-    prelude::*,
-    window::{close_on_esc, PrimaryWindow},
-};
+use std::time::Duration;
+
+use bevy::{prelude::*, window::close_on_esc};
 use bevy_ecs_tilemap::{
     prelude::{
         get_tilemap_center_transform, TilemapGridSize, TilemapId, TilemapSize, TilemapTexture,
@@ -11,32 +10,58 @@ use bevy_ecs_tilemap::{
     TilemapBundle, TilemapPlugin,
 };
 
+use crate::app::AppState;
+
 use self::{
+    archetype::{EnemyArchetypeAssetsPlugin, EnemyArchetypes},
+    boss::{BossPlugin, BossVisualsPlugin},
     camera::GameCameraPlugin,
-    enemy::EnemyPlugin,
-    health::HealthPlugin,
-    physics::PhysicsPlugin,
-    player::{Player, PlayerPlugin},
+    clock::GameClockPlugin,
+    enemy::{EnemyPlugin, EnemyVisualsPlugin, MeleePhase},
+    health::{HealthBarPlugin, HealthPlugin},
+    physics::{PhysicsPlugin, PhysicsVisualsPlugin},
+    intent::PlayerIntent,
+    pickup::{PickupPlugin, PickupVisualsPlugin},
+    player::{Player, PlayerPlugin, PlayerVisualsPlugin},
     projectile::ProjectilePlugin, animated::AnimatedPlugin,
+    replay::ReplayPlugin,
+    rng::RunRngPlugin,
+    ui::GameUiPlugin,
+    wave::{WaveDirectorPlugin, WaveSchedule, WaveScheduleAssetsPlugin},
 };
 
 pub mod animated;
+pub mod archetype;
+pub mod boss;
 pub mod camera;
+pub mod clock;
 pub mod enemy;
 pub mod health;
+#[cfg(test)]
+pub mod headless;
+pub mod intent;
 pub mod physics;
+pub mod pickup;
 pub mod player;
 pub mod projectile;
+pub mod replay;
+pub mod rng;
+pub mod spawn;
 pub mod ui;
+pub mod wave;
 pub mod weapon;
 
+/// `BeginTick` through `EndTick` are the simulation and run in `FixedUpdate`, so outcomes don't
+/// depend on frame rate. `Animation` and `Ui` are presentation and run once per frame in `Update`.
 #[derive(SystemSet, Hash, PartialEq, Eq, Clone, Debug)]
 enum GameSet {
+    BeginTick,
     PlayerInput,
-    Physics,
     Ai,
+    Physics,
     DealDamage,
     ResolveDamage,
+    EndTick,
     Animation,
     Ui,
 }
@@ -51,6 +76,167 @@ impl EntityTookDamage {
     pub fn new(entity: Entity, damage: i32) -> Self {
         Self { entity, damage }
     }
+
+    pub fn entity(&self) -> Entity {
+        self.entity
+    }
+
+    pub fn damage(&self) -> i32 {
+        self.damage
+    }
+}
+
+#[derive(Event, Debug)]
+pub struct EntityDied {
+    entity: Entity,
+    position: Vec3,
+}
+
+impl EntityDied {
+    pub fn new(entity: Entity, position: Vec3) -> Self {
+        Self { entity, position }
+    }
+
+    pub fn entity(&self) -> Entity {
+        self.entity
+    }
+
+    pub fn position(&self) -> Vec3 {
+        self.position
+    }
+}
+
+#[derive(Event, Debug)]
+pub struct WeaponFired {
+    position: Vec3,
+    recoil: f32,
+}
+
+impl WeaponFired {
+    pub fn new(position: Vec3, recoil: f32) -> Self {
+        Self { position, recoil }
+    }
+
+    pub fn position(&self) -> Vec3 {
+        self.position
+    }
+
+    /// How hard the weapon kicks, from 0 for light weapons to 1 for the heaviest.
+    pub fn recoil(&self) -> f32 {
+        self.recoil
+    }
+}
+
+/// Sent each time an enemy moves on to the next part of its melee attack, for animation and
+/// audio to hook into.
+#[derive(Event, Debug)]
+pub struct EnemyMeleePhaseChanged {
+    entity: Entity,
+    phase: MeleePhase,
+}
+
+impl EnemyMeleePhaseChanged {
+    pub fn new(entity: Entity, phase: MeleePhase) -> Self {
+        Self { entity, phase }
+    }
+
+    pub fn entity(&self) -> Entity {
+        self.entity
+    }
+
+    pub fn phase(&self) -> MeleePhase {
+        self.phase
+    }
+}
+
+#[derive(Event, Debug)]
+pub struct WaveStarted {
+    wave: u32,
+}
+
+impl WaveStarted {
+    pub fn new(wave: u32) -> Self {
+        Self { wave }
+    }
+
+    /// Counts up from 1.
+    pub fn wave(&self) -> u32 {
+        self.wave
+    }
+}
+
+#[derive(Event, Debug)]
+pub struct WaveCleared {
+    wave: u32,
+}
+
+impl WaveCleared {
+    pub fn new(wave: u32) -> Self {
+        Self { wave }
+    }
+
+    pub fn wave(&self) -> u32 {
+        self.wave
+    }
+}
+
+/// Sent once as a boss enters the run, for the announcement and boss health bar.
+#[derive(Event, Debug)]
+pub struct BossArrived {
+    entity: Entity,
+}
+
+impl BossArrived {
+    pub fn new(entity: Entity) -> Self {
+        Self { entity }
+    }
+
+    pub fn entity(&self) -> Entity {
+        self.entity
+    }
+}
+
+#[derive(Event, Debug)]
+pub struct BossPhaseChanged {
+    entity: Entity,
+    phase: usize,
+}
+
+impl BossPhaseChanged {
+    pub fn new(entity: Entity, phase: usize) -> Self {
+        Self { entity, phase }
+    }
+
+    pub fn entity(&self) -> Entity {
+        self.entity
+    }
+
+    /// Counts up from 1 as health thresholds are crossed.
+    pub fn phase(&self) -> usize {
+        self.phase
+    }
+}
+
+/// Asks the camera to hold a zoom for a while, e.g. pulling out to frame a boss as it arrives.
+#[derive(Event, Debug)]
+pub struct CameraZoomRequested {
+    scale: f32,
+    duration: Duration,
+}
+
+impl CameraZoomRequested {
+    pub fn new(scale: f32, duration: Duration) -> Self {
+        Self { scale, duration }
+    }
+
+    /// Orthographic scale, above 1 shows more of the world.
+    pub fn scale(&self) -> f32 {
+        self.scale
+    }
+
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
 }
 
 #[derive(Component, Debug, PartialEq, Eq)]
@@ -59,6 +245,20 @@ pub enum EnitityAllegence {
     Enemy,
 }
 
+/// A run can't start until the enemy archetypes and wave schedule are in.
+pub fn simulation_data_loaded(
+    archetypes: Res<EnemyArchetypes>,
+    schedule: Res<WaveSchedule>,
+) -> bool {
+    !archetypes.is_empty() && !schedule.is_empty()
+}
+
+pub fn despawn_all<T: Component>(mut commands: Commands, query: Query<Entity, With<T>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
 fn setup_tiles(mut commands: Commands, asset_server: Res<AssetServer>) {
     let tiles_texture_handle: Handle<Image> = asset_server.load("tiles.png");
 
@@ -118,37 +318,87 @@ fn setup_tiles(mut commands: Commands, asset_server: Res<AssetServer>) {
     }
 }
 
-fn calculate_player_direction_from_mouse(cursor_position: &Vec2, window: &Window) -> Vec3 {
-    let width = window.width();
-    let height = window.height();
+/// Everything that decides the outcome of a run. Needs no window, renderer or assets, so it also
+/// runs headless under `MinimalPlugins`.
+pub struct GameSimulationPlugin;
 
-    Vec3::new(
-        cursor_position.x - width / 2.0,
-        -(cursor_position.y - height / 2.0),
-        0.0,
-    )
+impl Plugin for GameSimulationPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<EntityTookDamage>();
+        app.add_event::<EntityDied>();
+        app.add_event::<WeaponFired>();
+        app.add_event::<EnemyMeleePhaseChanged>();
+        app.add_event::<WaveStarted>();
+        app.add_event::<WaveCleared>();
+        app.add_event::<BossArrived>();
+        app.add_event::<BossPhaseChanged>();
+        app.add_event::<CameraZoomRequested>();
+        app.init_resource::<PlayerIntent>();
+        app.configure_sets(
+            FixedUpdate,
+            (
+                GameSet::BeginTick,
+                GameSet::PlayerInput,
+                GameSet::Ai,
+                GameSet::Physics,
+                GameSet::DealDamage,
+                GameSet::ResolveDamage,
+                GameSet::EndTick,
+            )
+                .chain(),
+        );
+        for set in [
+            GameSet::BeginTick,
+            GameSet::PlayerInput,
+            GameSet::Ai,
+            GameSet::Physics,
+            GameSet::DealDamage,
+            GameSet::ResolveDamage,
+            GameSet::EndTick,
+        ] {
+            app.configure_set(FixedUpdate, set.run_if(in_state(AppState::InGame)));
+        }
+        app.add_plugins((
+            GameClockPlugin,
+            PhysicsPlugin,
+            PlayerPlugin,
+            EnemyPlugin,
+            ProjectilePlugin,
+            HealthPlugin,
+            RunRngPlugin,
+            WaveDirectorPlugin,
+            BossPlugin,
+            PickupPlugin,
+        ));
+    }
 }
 
+/// The simulation plus everything needed to see and play it: sprites, tiles, camera, UI and live
+/// input.
 pub struct GamePlugin;
 
 impl Plugin for GamePlugin {
     fn build(&self, app: &mut App) {
-        app.add_event::<EntityTookDamage>();
-        app.configure_set(Update, GameSet::PlayerInput.before(GameSet::Physics));
-        app.configure_set(Update, GameSet::Physics.before(GameSet::DealDamage));
-        app.configure_set(Update, GameSet::DealDamage.before(GameSet::ResolveDamage));
-        app.configure_set(Update, GameSet::ResolveDamage.before(GameSet::Animation));
+        app.add_plugins(GameSimulationPlugin);
         app.configure_set(Update, GameSet::Animation.before(GameSet::Ui));
+        for set in [GameSet::Animation, GameSet::Ui] {
+            app.configure_set(Update, set.run_if(in_state(AppState::InGame)));
+        }
         app.add_plugins((
             GameCameraPlugin,
             TilemapPlugin,
-            PhysicsPlugin,
-            PlayerPlugin,
-            EnemyPlugin,
-            ProjectilePlugin,
-            HealthPlugin,
+            EnemyArchetypeAssetsPlugin,
+            WaveScheduleAssetsPlugin,
+            PhysicsVisualsPlugin,
+            PlayerVisualsPlugin,
+            EnemyVisualsPlugin,
+            BossVisualsPlugin,
+            PickupVisualsPlugin,
+            HealthBarPlugin,
             AnimatedPlugin,
+            GameUiPlugin,
+            ReplayPlugin,
         ));
-        app.add_systems(Startup, (setup_tiles));
+        app.add_systems(OnExit(AppState::MainMenu), (setup_tiles));
     }
 }
@@ -1,6 +1,8 @@
 use bevy::prelude::*;
 
-use super::{animated::AnimatedDirection, GameSet};
+use crate::app::AppState;
+
+use super::{animated::AnimatedDirection, clock::SimulationClock, GameSet};
 #[derive(Component)]
 pub struct Velocity(Vec3);
 
@@ -48,6 +50,13 @@ impl Collider {
         Self::Circle { radius }
     }
 
+    /// Radius of the smallest circle around the collider.
+    pub fn radius(&self) -> f32 {
+        match self {
+            Self::Circle { radius } => *radius,
+        }
+    }
+
     pub fn is_colliding(
         &self,
         transform: &Transform,
@@ -63,6 +72,26 @@ impl Collider {
             }
         }
     }
+
+    /// Whether a circle of `radius` swept from `start` to `end` would touch this collider.
+    pub fn intersects_segment(
+        &self,
+        transform: &Transform,
+        start: Vec3,
+        end: Vec3,
+        radius: f32,
+    ) -> bool {
+        match self {
+            Self::Circle { radius: own_radius } => {
+                let segment = end - start;
+                let along = (transform.translation - start).dot(segment)
+                    / segment.length_squared().max(f32::EPSILON);
+                let closest = start + segment * along.clamp(0.0, 1.0);
+
+                closest.distance_squared(transform.translation) < (own_radius + radius).powi(2)
+            }
+        }
+    }
 }
 
 #[derive(Bundle)]
@@ -81,9 +110,73 @@ fn render_debug(mut gizmos: Gizmos, collider_query: Query<(&Collider, &Transform
     }
 }
 
-pub fn update_positions(mut query: Query<(&mut Transform, &Velocity)>, time: Res<Time>) {
+/// Simulated positions either side of the latest tick. `Transform` holds the simulated position
+/// during `FixedUpdate` and a blend of the two while rendering, so motion stays smooth when the
+/// tick rate and refresh rate differ.
+#[derive(Component, Debug)]
+pub struct InterpolatedPosition {
+    previous: Vec3,
+    current: Vec3,
+}
+
+impl InterpolatedPosition {
+    pub fn new(translation: Vec3) -> Self {
+        Self {
+            previous: translation,
+            current: translation,
+        }
+    }
+
+    pub fn previous(&self) -> Vec3 {
+        self.previous
+    }
+
+    pub fn current(&self) -> Vec3 {
+        self.current
+    }
+}
+
+pub fn update_positions(
+    mut query: Query<(&mut Transform, &Velocity)>,
+    clock: Res<SimulationClock>,
+) {
     for (mut transform, velocity) in query.iter_mut() {
-        transform.translation += velocity.as_vec() * time.delta_seconds();
+        transform.translation += velocity.as_vec() * clock.delta_seconds();
+    }
+}
+
+fn begin_interpolation_tick(mut query: Query<(&mut Transform, &mut InterpolatedPosition)>) {
+    for (mut transform, mut position) in query.iter_mut() {
+        transform.translation = position.current;
+        position.previous = position.current;
+    }
+}
+
+fn end_interpolation_tick(
+    mut commands: Commands,
+    mut query: Query<(&Transform, &mut InterpolatedPosition)>,
+    new_query: Query<(Entity, &Transform), (With<Velocity>, Without<InterpolatedPosition>)>,
+) {
+    for (transform, mut position) in query.iter_mut() {
+        position.current = transform.translation;
+    }
+
+    for (entity, transform) in new_query.iter() {
+        commands
+            .entity(entity)
+            .insert(InterpolatedPosition::new(transform.translation));
+    }
+}
+
+fn interpolate_positions(
+    fixed_time: Res<FixedTime>,
+    mut query: Query<(&mut Transform, &InterpolatedPosition)>,
+) {
+    let alpha = (fixed_time.accumulated().as_secs_f32() / fixed_time.period.as_secs_f32())
+        .clamp(0.0, 1.0);
+
+    for (mut transform, position) in query.iter_mut() {
+        transform.translation = position.previous().lerp(position.current(), alpha);
     }
 }
 
@@ -91,7 +184,22 @@ pub struct PhysicsPlugin;
 
 impl Plugin for PhysicsPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Update, (update_positions).in_set(GameSet::Physics));
+        app.add_systems(FixedUpdate, (update_positions).in_set(GameSet::Physics));
+        app.add_systems(FixedUpdate, (begin_interpolation_tick).in_set(GameSet::BeginTick));
+        app.add_systems(FixedUpdate, (end_interpolation_tick).in_set(GameSet::EndTick));
+    }
+}
+
+pub struct PhysicsVisualsPlugin;
+
+impl Plugin for PhysicsVisualsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (interpolate_positions)
+                .before(GameSet::Animation)
+                .run_if(in_state(AppState::InGame)),
+        );
         #[cfg(debug_assertions)]
         app.add_systems(Update, (render_debug,).in_set(GameSet::Ui));
     }
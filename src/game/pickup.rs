@@ -0,0 +1,134 @@
+use bevy::{prelude::*, sprite::MaterialMesh2dBundle};
+use serde::Deserialize;
+
+use crate::app::AppState;
+
+use super::{despawn_all, health::Health, physics::Collider, player::Player, GameSet};
+
+const PICKUP_RADIUS: f32 = 12.0;
+
+/// What a pickup does for the player when collected.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub enum Reward {
+    /// Restores health, never above the player's max.
+    Heal(i32),
+    /// Raises max health and fills the extra room.
+    MaxHealth(i32),
+}
+
+impl Reward {
+    fn apply(&self, health: &mut Health) {
+        match *self {
+            Self::Heal(amount) => health.heal(amount),
+            Self::MaxHealth(amount) => health.raise_max(amount),
+        }
+    }
+}
+
+/// Lies where it dropped until the player walks over it.
+#[derive(Component, Debug)]
+pub struct Pickup {
+    reward: Reward,
+}
+
+impl Pickup {
+    pub fn new(reward: Reward) -> Self {
+        Self { reward }
+    }
+
+    pub fn reward(&self) -> Reward {
+        self.reward
+    }
+}
+
+#[derive(Bundle)]
+pub struct PickupBundle {
+    pickup: Pickup,
+    collider: Collider,
+    transform: TransformBundle,
+}
+
+impl PickupBundle {
+    pub fn new(reward: Reward, position: Vec3) -> Self {
+        Self {
+            pickup: Pickup::new(reward),
+            collider: Collider::circle(PICKUP_RADIUS),
+            transform: TransformBundle::from_transform(Transform::from_translation(position)),
+        }
+    }
+}
+
+fn collect_pickups(
+    mut commands: Commands,
+    mut player_query: Query<(&Collider, &Transform, &mut Health), With<Player>>,
+    pickup_query: Query<(Entity, &Pickup, &Collider, &Transform), Without<Player>>,
+) {
+    let Ok((player_collider, player_transform, mut health)) = player_query.get_single_mut() else {
+        return;
+    };
+
+    for (entity, pickup, collider, transform) in pickup_query.iter() {
+        if player_collider.is_colliding(player_transform, collider, transform) {
+            pickup.reward().apply(&mut health);
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+/// Every pickup shares one mesh and a material per kind of reward.
+#[derive(Resource)]
+struct PickupAssets {
+    mesh: Handle<Mesh>,
+    heal: Handle<ColorMaterial>,
+    max_health: Handle<ColorMaterial>,
+}
+
+fn load_pickup_assets(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    commands.insert_resource(PickupAssets {
+        mesh: meshes.add(shape::Circle::new(PICKUP_RADIUS).into()),
+        heal: materials.add(Color::rgb(0.2, 0.9, 0.3).into()),
+        max_health: materials.add(Color::rgb(1.0, 0.8, 0.2).into()),
+    });
+}
+
+fn add_pickup_visuals(
+    mut commands: Commands,
+    assets: Res<PickupAssets>,
+    pickup_query: Query<(Entity, &Pickup, &Transform), Added<Pickup>>,
+) {
+    for (entity, pickup, transform) in pickup_query.iter() {
+        let material = match pickup.reward() {
+            Reward::Heal(_) => assets.heal.clone(),
+            Reward::MaxHealth(_) => assets.max_health.clone(),
+        };
+
+        commands.entity(entity).insert(MaterialMesh2dBundle {
+            mesh: assets.mesh.clone().into(),
+            material,
+            transform: *transform,
+            ..default()
+        });
+    }
+}
+
+pub struct PickupPlugin;
+
+impl Plugin for PickupPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(AppState::GameOver), despawn_all::<Pickup>);
+        app.add_systems(FixedUpdate, (collect_pickups).in_set(GameSet::Physics));
+    }
+}
+
+pub struct PickupVisualsPlugin;
+
+impl Plugin for PickupVisualsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, load_pickup_assets);
+        app.add_systems(Update, (add_pickup_visuals).in_set(GameSet::Animation));
+    }
+}
@@ -1,16 +1,19 @@
-use bevy::{prelude::*, transform::commands, window::PrimaryWindow};
+use bevy::{prelude::*, transform::commands};
 use bevy_aseprite::{anim::AsepriteAnimation, AsepriteBundle};
 
+use crate::app::AppState;
+
 use super::{
     animated::{Animated, AnimatedBundle, AnimatedDirection},
-    calculate_player_direction_from_mouse,
-    camera::{GameCameraGoal, CAMERA_OFFSET_FROM_PLAYER},
+    camera::GameCameraGoal,
+    clock::SimulationClock,
     health::{
         spawn_health_bar, {Health, HealthBar},
     },
+    intent::PlayerIntent,
     physics::{Collider, Velocity},
     weapon::PlayerWeapon,
-    EnitityAllegence, GameSet,
+    despawn_all, EnitityAllegence, GameSet, WeaponFired,
 };
 
 mod sprites {
@@ -49,43 +52,53 @@ impl Player {
     pub fn weapon_two(&self) -> Option<&PlayerWeapon> {
         self.weapon_two.as_ref()
     }
+
+    pub fn weapon_two_mut(&mut self) -> Option<&mut PlayerWeapon> {
+        self.weapon_two.as_mut()
+    }
 }
 
 #[derive(Bundle)]
 pub struct PlayerBundle {
     collider: Collider,
-    aesprite: AsepriteBundle,
+    transform: TransformBundle,
     player: Player,
     allegence: EnitityAllegence,
     health: Health,
     velocity: Velocity,
-    animated: AnimatedBundle,
 }
 
-fn spawn_player(
+pub fn spawn_player(mut commands: Commands) {
+    commands.spawn(PlayerBundle {
+        collider: Collider::circle(32.),
+        transform: TransformBundle::from_transform(Transform {
+            scale: Vec3::splat(3.),
+            translation: Vec3::new(0., 0., 1.),
+            ..Default::default()
+        }),
+        player: Player::new(),
+        allegence: EnitityAllegence::Player,
+        health: Health::new(100),
+        velocity: Velocity::zero(),
+    });
+}
+
+fn add_player_visuals(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
-    meshes: ResMut<Assets<Mesh>>,
-    materials: ResMut<Assets<ColorMaterial>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    player_query: Query<(Entity, &Transform), Added<Player>>,
 ) {
-    let entity = commands
-        .spawn(PlayerBundle {
-            collider: Collider::circle(32.),
-            aesprite: AsepriteBundle {
+    for (entity, transform) in player_query.iter() {
+        commands.entity(entity).insert((
+            AsepriteBundle {
                 aseprite: asset_server.load(sprites::PlayerAnim::PATH),
                 animation: AsepriteAnimation::from(sprites::PlayerAnim::tags::IDLE),
-                transform: Transform {
-                    scale: Vec3::splat(3.),
-                    translation: Vec3::new(0., 0., 1.),
-                    ..Default::default()
-                },
+                transform: *transform,
                 ..Default::default()
             },
-            player: Player::new(),
-            allegence: EnitityAllegence::Player,
-            health: Health::new(100),
-            velocity: Velocity::zero(),
-            animated: AnimatedBundle {
+            AnimatedBundle {
                 animated: Animated::new(
                     Some(sprites::PlayerAnim::tags::IDLE.to_string()),
                     sprites::PlayerAnim::tags::RUN_DOWN_LEFT.to_string(),
@@ -95,47 +108,44 @@ fn spawn_player(
                 ),
                 animated_direction: AnimatedDirection::default(),
             },
-        })
-        .id();
-
-    spawn_health_bar(commands, meshes, materials, HealthBar::new(entity, 24.));
+        ));
+
+        spawn_health_bar(
+            &mut commands,
+            &mut meshes,
+            &mut materials,
+            HealthBar::new(entity, 24.),
+        );
+    }
 }
 
 pub fn player_input(
-    commands: Commands,
-    window: Query<&Window, With<PrimaryWindow>>,
-    keyboard_input: Res<Input<KeyCode>>,
-    buttons: Res<Input<MouseButton>>,
+    mut commands: Commands,
+    clock: Res<SimulationClock>,
+    intent: Res<PlayerIntent>,
+    mut weapon_fired_events: EventWriter<WeaponFired>,
     mut player_query: Query<(&mut Player, &Transform, &mut Velocity), Without<GameCameraGoal>>,
 ) {
     if let Ok((mut player, transform, mut velocity)) = player_query.get_single_mut() {
-        let mut direction = Vec3::ZERO;
-        if keyboard_input.pressed(KeyCode::Left) || keyboard_input.pressed(KeyCode::A) {
-            direction.x -= 1.0;
-        }
-        if keyboard_input.pressed(KeyCode::Right) || keyboard_input.pressed(KeyCode::D) {
-            direction.x += 1.0;
-        }
-        if keyboard_input.pressed(KeyCode::Down) || keyboard_input.pressed(KeyCode::S) {
-            direction.y -= 1.0;
-        }
-        if keyboard_input.pressed(KeyCode::Up) || keyboard_input.pressed(KeyCode::W) {
-            direction.y += 1.0;
-        }
-        direction = direction.normalize_or_zero();
-        *velocity = Velocity::from_vec(direction * player.speed());
-
-        if let Some(cursor_position) = window.single().cursor_position() {
-            let cursor_offset_from_center =
-                calculate_player_direction_from_mouse(&cursor_position, window.single())
-                    .clamp_length_max(CAMERA_OFFSET_FROM_PLAYER);
-
-            if buttons.pressed(MouseButton::Left) && player.weapon_one().can_attack() {
-                player.weapon_one_mut().attack(
-                    commands,
-                    transform.translation,
-                    cursor_offset_from_center.normalize_or_zero(),
-                );
+        *velocity = Velocity::from_vec(intent.movement() * player.speed());
+
+        if let Some(facing) = intent.facing() {
+            let now = clock.elapsed_seconds();
+
+            if intent.fire_primary() && player.weapon_one().can_attack(now) {
+                let weapon = player.weapon_one_mut();
+                weapon.attack(&mut commands, now, transform.translation, facing);
+                weapon_fired_events.send(WeaponFired::new(transform.translation, weapon.recoil()));
+            }
+
+            if intent.fire_secondary() {
+                if let Some(weapon) = player.weapon_two_mut() {
+                    if weapon.can_attack(now) {
+                        weapon.attack(&mut commands, now, transform.translation, facing);
+                        weapon_fired_events
+                            .send(WeaponFired::new(transform.translation, weapon.recoil()));
+                    }
+                }
             }
         }
     }
@@ -145,7 +155,17 @@ pub struct PlayerPlugin;
 
 impl Plugin for PlayerPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Startup, spawn_player);
-        app.add_systems(Update, (player_input).in_set(GameSet::PlayerInput));
+        app.add_systems(OnExit(AppState::MainMenu), spawn_player);
+        app.add_systems(OnEnter(AppState::GameOver), despawn_all::<Player>);
+        app.add_systems(OnExit(AppState::GameOver), spawn_player);
+        app.add_systems(FixedUpdate, (player_input).in_set(GameSet::PlayerInput));
+    }
+}
+
+pub struct PlayerVisualsPlugin;
+
+impl Plugin for PlayerVisualsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, (add_player_visuals).in_set(GameSet::Animation));
     }
 }
@@ -1,8 +1,10 @@
 use bevy::prelude::*;
 
+use crate::app::AppState;
+
 use super::{
     physics::{Collider, Velocity},
-    EnitityAllegence, GameSet, EntityTookDamage,
+    despawn_all, EnitityAllegence, GameSet, EntityTookDamage,
 };
 
 #[derive(Component)]
@@ -20,6 +22,19 @@ impl Projectile {
     }
 }
 
+/// Projectiles that miss are gone once they're this far from where they were fired.
+#[derive(Component, Debug)]
+pub struct ProjectileRange {
+    origin: Vec3,
+    range: f32,
+}
+
+impl ProjectileRange {
+    pub fn new(origin: Vec3, range: f32) -> Self {
+        Self { origin, range }
+    }
+}
+
 #[derive(Bundle)]
 pub struct ProjectileBundle {
     pub transform: Transform,
@@ -27,6 +42,7 @@ pub struct ProjectileBundle {
     pub collider: Collider,
     pub projectile: Projectile,
     pub allegence: EnitityAllegence,
+    pub range: ProjectileRange,
 }
 
 pub fn projectile_hurt_entity(
@@ -68,11 +84,23 @@ pub fn projectile_hurt_entity(
     }
 }
 
+fn expire_projectiles(
+    mut commands: Commands,
+    projectile_query: Query<(Entity, &ProjectileRange, &Transform)>,
+) {
+    for (entity, range, transform) in projectile_query.iter() {
+        if transform.translation.distance(range.origin) > range.range {
+            commands.entity(entity).despawn();
+        }
+    }
+}
 
 pub struct ProjectilePlugin;
 
 impl Plugin for ProjectilePlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Update, (projectile_hurt_entity).in_set(GameSet::DealDamage));
+        app.add_systems(FixedUpdate, (projectile_hurt_entity).in_set(GameSet::DealDamage));
+        app.add_systems(FixedUpdate, (expire_projectiles).in_set(GameSet::EndTick));
+        app.add_systems(OnEnter(AppState::GameOver), despawn_all::<Projectile>);
     }
 }
@@ -0,0 +1,474 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use bevy::{app::AppExit, prelude::*};
+use thiserror::Error;
+
+use crate::{
+    app::{launch_arg, AppState},
+    settings::{Settings, SIMULATION_TICK_RATES},
+};
+
+use super::{
+    clock::configure_tick_rate,
+    intent::{gather_player_intent, PlayerIntent},
+    player::player_input,
+    rng::{RunRng, SeedOverride},
+    simulation_data_loaded, GameSet,
+};
+
+const REPLAY_MAGIC: &[u8; 4] = b"BLRP";
+const REPLAY_VERSION: u16 = 1;
+
+const REPLAY_DIRECTORY: &str = "blahbarian/replays";
+
+const BUTTON_FIRE_PRIMARY: u8 = 1 << 0;
+const BUTTON_FIRE_SECONDARY: u8 = 1 << 1;
+const BUTTON_HAS_AIM: u8 = 1 << 2;
+
+#[derive(Error, Debug)]
+pub enum ReplayError {
+    #[error("could not find a data directory for this platform")]
+    NoDataDirectory,
+    #[error("could not access replay file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("not a replay file")]
+    BadMagic,
+    #[error("replay version {0} is not supported")]
+    UnsupportedVersion(u16),
+    #[error("replay file is truncated")]
+    Truncated,
+    #[error("replay tick rate {0} is out of range")]
+    InvalidTickRate(f32),
+}
+
+/// One run's worth of player intent, one entry per simulation tick. Consecutive identical ticks
+/// are stored once with a repeat count, which keeps files small since input rarely changes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Replay {
+    seed: u64,
+    tick_rate: f32,
+    ticks: Vec<(u32, PlayerIntent)>,
+}
+
+impl Replay {
+    pub fn new(seed: u64, tick_rate: f32) -> Self {
+        Self {
+            seed,
+            tick_rate,
+            ticks: vec![],
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn tick_rate(&self) -> f32 {
+        self.tick_rate
+    }
+
+    pub fn len(&self) -> u64 {
+        self.ticks.iter().map(|(repeat, _)| *repeat as u64).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ticks.is_empty()
+    }
+
+    pub fn push(&mut self, intent: PlayerIntent) {
+        match self.ticks.last_mut() {
+            Some((repeat, last)) if *last == intent && *repeat < u32::MAX => *repeat += 1,
+            _ => self.ticks.push((1, intent)),
+        }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![];
+
+        bytes.extend_from_slice(REPLAY_MAGIC);
+        bytes.extend_from_slice(&REPLAY_VERSION.to_le_bytes());
+        bytes.extend_from_slice(&self.seed.to_le_bytes());
+        bytes.extend_from_slice(&self.tick_rate.to_le_bytes());
+        bytes.extend_from_slice(&(self.ticks.len() as u32).to_le_bytes());
+
+        for (repeat, intent) in self.ticks.iter() {
+            let aim = intent.aim().unwrap_or_default();
+
+            let mut buttons = 0;
+            if intent.fire_primary() {
+                buttons |= BUTTON_FIRE_PRIMARY;
+            }
+            if intent.fire_secondary() {
+                buttons |= BUTTON_FIRE_SECONDARY;
+            }
+            if intent.aim().is_some() {
+                buttons |= BUTTON_HAS_AIM;
+            }
+
+            bytes.extend_from_slice(&repeat.to_le_bytes());
+            bytes.extend_from_slice(&intent.movement().x.to_le_bytes());
+            bytes.extend_from_slice(&intent.movement().y.to_le_bytes());
+            bytes.extend_from_slice(&aim.x.to_le_bytes());
+            bytes.extend_from_slice(&aim.y.to_le_bytes());
+            bytes.push(buttons);
+        }
+
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ReplayError> {
+        let mut reader = ByteReader { bytes };
+
+        if reader.take::<4>()? != *REPLAY_MAGIC {
+            return Err(ReplayError::BadMagic);
+        }
+
+        let version = u16::from_le_bytes(reader.take()?);
+        if version != REPLAY_VERSION {
+            return Err(ReplayError::UnsupportedVersion(version));
+        }
+
+        let seed = u64::from_le_bytes(reader.take()?);
+        let tick_rate = f32::from_le_bytes(reader.take()?);
+        if !SIMULATION_TICK_RATES.contains(&tick_rate) {
+            return Err(ReplayError::InvalidTickRate(tick_rate));
+        }
+
+        let tick_count = u32::from_le_bytes(reader.take()?);
+
+        let mut replay = Self::new(seed, tick_rate);
+
+        for _ in 0..tick_count {
+            let repeat = u32::from_le_bytes(reader.take()?);
+            let movement = Vec3::new(
+                f32::from_le_bytes(reader.take()?),
+                f32::from_le_bytes(reader.take()?),
+                0.0,
+            );
+            let aim = Vec3::new(
+                f32::from_le_bytes(reader.take()?),
+                f32::from_le_bytes(reader.take()?),
+                0.0,
+            );
+            let [buttons] = reader.take()?;
+
+            let intent = PlayerIntent::new(
+                movement,
+                Some(aim).filter(|_| buttons & BUTTON_HAS_AIM != 0),
+                buttons & BUTTON_FIRE_PRIMARY != 0,
+                buttons & BUTTON_FIRE_SECONDARY != 0,
+            );
+
+            replay.ticks.push((repeat, intent));
+        }
+
+        Ok(replay)
+    }
+
+    pub fn load(path: &Path) -> Result<Self, ReplayError> {
+        Self::from_bytes(&fs::read(path)?)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), ReplayError> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        fs::write(path, self.to_bytes())?;
+
+        Ok(())
+    }
+}
+
+struct ByteReader<'a> {
+    bytes: &'a [u8],
+}
+
+impl ByteReader<'_> {
+    fn take<const N: usize>(&mut self) -> Result<[u8; N], ReplayError> {
+        if self.bytes.len() < N {
+            return Err(ReplayError::Truncated);
+        }
+
+        let (taken, rest) = self.bytes.split_at(N);
+        self.bytes = rest;
+
+        Ok(taken.try_into().expect("split_at returns exactly N bytes"))
+    }
+}
+
+/// Records the current run. Created lazily on the first tick so the run seed is known.
+#[derive(Resource, Debug, Default)]
+pub struct ReplayRecorder {
+    replay: Option<Replay>,
+}
+
+/// Feeds a loaded replay back in place of live input, started with `--replay <path>`. The run
+/// begins as soon as the game data has loaded.
+#[derive(Resource, Debug)]
+pub struct ReplayPlayback {
+    replay: Replay,
+    entry: usize,
+    repeated: u32,
+}
+
+impl ReplayPlayback {
+    pub fn new(replay: Replay) -> Self {
+        Self {
+            replay,
+            entry: 0,
+            repeated: 0,
+        }
+    }
+
+    fn next_intent(&mut self) -> Option<PlayerIntent> {
+        let (repeat, intent) = *self.replay.ticks.get(self.entry)?;
+
+        self.repeated += 1;
+        if self.repeated >= repeat {
+            self.entry += 1;
+            self.repeated = 0;
+        }
+
+        Some(intent)
+    }
+}
+
+fn replay_path(seed: u64) -> Result<PathBuf, ReplayError> {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default();
+
+    dirs::data_dir()
+        .map(|dir| {
+            dir.join(REPLAY_DIRECTORY)
+                .join(format!("run-{timestamp}-{seed}.replay"))
+        })
+        .ok_or(ReplayError::NoDataDirectory)
+}
+
+fn record_player_intent(
+    intent: Res<PlayerIntent>,
+    rng: Res<RunRng>,
+    settings: Res<Settings>,
+    mut recorder: ResMut<ReplayRecorder>,
+) {
+    recorder
+        .replay
+        .get_or_insert_with(|| Replay::new(rng.seed(), settings.simulation_tick_rate()))
+        .push(*intent);
+}
+
+fn save_recording(mut recorder: ResMut<ReplayRecorder>) {
+    let Some(replay) = recorder.replay.take() else {
+        return;
+    };
+
+    let result = replay_path(replay.seed()).and_then(|path| {
+        replay.save(&path)?;
+        Ok(path)
+    });
+
+    match result {
+        Ok(path) => info!("Saved replay of {} ticks to {}", replay.len(), path.display()),
+        Err(error) => warn!("Failed to save replay: {error}"),
+    }
+}
+
+fn save_recording_on_exit(exit_events: EventReader<AppExit>, recorder: ResMut<ReplayRecorder>) {
+    if !exit_events.is_empty() {
+        save_recording(recorder);
+    }
+}
+
+fn feed_replay_intent(mut playback: ResMut<ReplayPlayback>, mut intent: ResMut<PlayerIntent>) {
+    match playback.next_intent() {
+        Some(next_intent) => *intent = next_intent,
+        None => *intent = PlayerIntent::default(),
+    }
+}
+
+fn start_playback(
+    playback: Res<ReplayPlayback>,
+    mut seed_override: ResMut<SeedOverride>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    seed_override.set(playback.replay.seed());
+    next_state.set(AppState::InGame);
+}
+
+fn force_replay_tick_rate(playback: Res<ReplayPlayback>, mut fixed_time: ResMut<FixedTime>) {
+    fixed_time.period = std::time::Duration::from_secs_f32(1.0 / playback.replay.tick_rate());
+}
+
+pub struct ReplayPlugin;
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        if let Some(path) = launch_arg("--replay") {
+            match Replay::load(Path::new(&path)) {
+                Ok(replay) => {
+                    info!("Playing replay {path} with seed {}", replay.seed());
+                    app.insert_resource(ReplayPlayback::new(replay));
+                }
+                Err(error) => error!("Failed to load replay {path}: {error}"),
+            }
+        }
+
+        let is_replaying = resource_exists::<ReplayPlayback>();
+
+        app.init_resource::<ReplayRecorder>();
+        app.add_systems(
+            Update,
+            start_playback.run_if(
+                is_replaying
+                    .clone()
+                    .and_then(in_state(AppState::MainMenu))
+                    .and_then(simulation_data_loaded),
+            ),
+        );
+        app.add_systems(
+            PreUpdate,
+            force_replay_tick_rate
+                .after(configure_tick_rate)
+                .run_if(is_replaying.clone()),
+        );
+        app.add_systems(
+            FixedUpdate,
+            (
+                gather_player_intent.run_if(not(is_replaying.clone())),
+                feed_replay_intent.run_if(is_replaying.clone()),
+                record_player_intent.run_if(not(is_replaying)),
+            )
+                .chain()
+                .before(player_input)
+                .in_set(GameSet::PlayerInput),
+        );
+        app.add_systems(OnEnter(AppState::GameOver), save_recording);
+        app.add_systems(Last, save_recording_on_exit);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn walking(x: f32) -> PlayerIntent {
+        PlayerIntent::new(Vec3::new(x, 0., 0.), None, false, false)
+    }
+
+    /// A couple of ticks covering every field, with both kinds of aim.
+    fn recorded() -> Replay {
+        let mut replay = Replay::new(42, 60.);
+
+        replay.push(walking(1.));
+        replay.push(walking(1.));
+        // Aiming at the player's own feet is still aiming
+        replay.push(PlayerIntent::new(Vec3::ZERO, Some(Vec3::ZERO), true, false));
+        replay.push(PlayerIntent::new(
+            Vec3::new(0., -0.5, 0.),
+            Some(Vec3::new(32., 16., 0.)),
+            false,
+            true,
+        ));
+
+        replay
+    }
+
+    #[test]
+    fn replay_round_trips_through_bytes() {
+        let replay = recorded();
+        let loaded = Replay::from_bytes(&replay.to_bytes()).unwrap();
+
+        assert_eq!(loaded, replay);
+        assert_eq!(loaded.ticks[0].1.aim(), None);
+        assert_eq!(loaded.ticks[1].1.aim(), Some(Vec3::ZERO));
+    }
+
+    #[test]
+    fn repeated_ticks_are_stored_once() {
+        let mut replay = Replay::new(42, 60.);
+
+        for x in [1., 1., 1., 0., 1.] {
+            replay.push(walking(x));
+        }
+
+        assert_eq!(
+            replay.ticks,
+            vec![(3, walking(1.)), (1, walking(0.)), (1, walking(1.))]
+        );
+        assert_eq!(replay.len(), 5);
+    }
+
+    #[test]
+    fn playback_walks_through_repeat_counts() {
+        let mut playback = ReplayPlayback::new(recorded());
+
+        let intents: Vec<_> = std::iter::from_fn(|| playback.next_intent()).collect();
+
+        assert_eq!(intents.len(), 4);
+        assert_eq!(intents[0], walking(1.));
+        assert_eq!(intents[1], walking(1.));
+        assert!(intents[2].fire_primary());
+        assert!(intents[3].fire_secondary());
+        assert_eq!(playback.next_intent(), None);
+    }
+
+    #[test]
+    fn other_files_are_not_replays() {
+        let mut bytes = recorded().to_bytes();
+        bytes[..4].copy_from_slice(b"RIFF");
+
+        assert!(matches!(
+            Replay::from_bytes(&bytes),
+            Err(ReplayError::BadMagic)
+        ));
+    }
+
+    #[test]
+    fn newer_versions_are_rejected() {
+        let mut bytes = recorded().to_bytes();
+        bytes[4..6].copy_from_slice(&(REPLAY_VERSION + 1).to_le_bytes());
+
+        assert!(matches!(
+            Replay::from_bytes(&bytes),
+            Err(ReplayError::UnsupportedVersion(version)) if version == REPLAY_VERSION + 1
+        ));
+    }
+
+    #[test]
+    fn cut_off_files_are_truncated() {
+        let bytes = recorded().to_bytes();
+
+        for length in [0, 3, bytes.len() - 1] {
+            assert!(
+                matches!(
+                    Replay::from_bytes(&bytes[..length]),
+                    Err(ReplayError::Truncated)
+                ),
+                "{length}"
+            );
+        }
+    }
+
+    #[test]
+    fn tick_rates_outside_the_settings_range_are_rejected() {
+        for tick_rate in [0., 1000., f32::NAN] {
+            let mut bytes = recorded().to_bytes();
+            bytes[14..18].copy_from_slice(&f32::to_le_bytes(tick_rate));
+
+            assert!(
+                matches!(
+                    Replay::from_bytes(&bytes),
+                    Err(ReplayError::InvalidTickRate(_))
+                ),
+                "{tick_rate}"
+            );
+        }
+    }
+}
@@ -0,0 +1,107 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use bevy_prng::ChaCha8Rng;
+use bevy_rand::resource::GlobalEntropy;
+use rand_core::{RngCore, SeedableRng};
+
+use crate::{
+    app::{launch_arg, AppState},
+    settings::Settings,
+};
+
+/// Independent random sequences within a run. Each stream is seeded from the run seed and its
+/// own id, so a new consumer on one stream never shifts the numbers another stream produces.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RngStream {
+    Spawning,
+    Loot,
+    Crits,
+}
+
+impl RngStream {
+    fn id(&self) -> u64 {
+        match self {
+            Self::Spawning => 1,
+            Self::Loot => 2,
+            Self::Crits => 3,
+        }
+    }
+}
+
+#[derive(Resource)]
+pub struct RunRng {
+    seed: u64,
+    streams: HashMap<RngStream, ChaCha8Rng>,
+}
+
+impl RunRng {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            streams: HashMap::new(),
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn stream(&mut self, stream: RngStream) -> &mut ChaCha8Rng {
+        let seed = self.seed;
+
+        self.streams.entry(stream).or_insert_with(|| {
+            // Golden ratio multiplier spreads consecutive ids across the whole seed space
+            ChaCha8Rng::seed_from_u64(seed ^ stream.id().wrapping_mul(0x9E37_79B9_7F4A_7C15))
+        })
+    }
+}
+
+/// Seed passed on the command line with `--seed <u64>`, or taken from a replay being played.
+/// Takes priority over `Settings`.
+#[derive(Resource, Debug)]
+pub struct SeedOverride(Option<u64>);
+
+impl SeedOverride {
+    pub fn set(&mut self, seed: u64) {
+        self.0 = Some(seed);
+    }
+}
+
+fn seed_from_args() -> Option<u64> {
+    let value = launch_arg("--seed")?;
+
+    match value.parse() {
+        Ok(seed) => Some(seed),
+        Err(_) => {
+            warn!("Ignoring invalid seed {value:?}");
+            None
+        }
+    }
+}
+
+fn start_run_rng(
+    mut commands: Commands,
+    seed_override: Res<SeedOverride>,
+    settings: Res<Settings>,
+    mut entropy: ResMut<GlobalEntropy<ChaCha8Rng>>,
+) {
+    let seed = seed_override
+        .0
+        .or(settings.seed())
+        .unwrap_or_else(|| entropy.next_u64());
+
+    info!("Starting run with seed {seed}");
+
+    commands.insert_resource(RunRng::new(seed));
+}
+
+pub struct RunRngPlugin;
+
+impl Plugin for RunRngPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(SeedOverride(seed_from_args()));
+        app.add_systems(OnExit(AppState::MainMenu), start_run_rng);
+        app.add_systems(OnExit(AppState::GameOver), start_run_rng);
+    }
+}
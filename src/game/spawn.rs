@@ -0,0 +1,307 @@
+use std::{f32::consts::TAU, iter};
+
+use bevy::{ecs::system::SystemParam, prelude::*};
+use rand_core::RngCore;
+
+use crate::settings::Settings;
+
+use super::{
+    archetype::{EnemyArchetype, EnemyArchetypes},
+    camera::{frame_within, max_view_size},
+    enemy::EnemyPool,
+    physics::Collider,
+    player::Player,
+    projectile::Projectile,
+    rng::{RngStream, RunRng},
+};
+
+/// The most of the world the camera shows unzoomed, whatever the window size.
+pub const VIEW_SIZE: Vec2 = Vec2::new(1280.0, 720.0);
+
+/// Extra gap between the edge of the view and a spawning enemy's collider.
+const SPAWN_MARGIN: f32 = 16.0;
+
+/// Candidates tried per spawn before giving up until the next tick.
+const SPAWN_ATTEMPTS: u32 = 8;
+
+/// What the player could possibly see and where enemies may stand, in world space. The view is
+/// worked out from the player's position and the camera's [`max_view_size`], framed within the
+/// walkable area the same way the camera is, rather than read back from the camera. Whatever the
+/// camera is actually showing fits inside it, and spawns stay independent of frame rate, window
+/// size and zoom, so replays place them the same. Without a walkable area anywhere goes.
+#[derive(Resource, Debug, Clone, Copy, PartialEq)]
+pub struct SpawnArea {
+    view_size: Vec2,
+    walkable: Option<Rect>,
+}
+
+impl SpawnArea {
+    pub fn new(view_size: Vec2, walkable: Option<Rect>) -> Self {
+        Self {
+            view_size,
+            walkable,
+        }
+    }
+
+    pub fn view(&self, player_position: Vec2) -> Rect {
+        let half_size = self.view_size / 2.0;
+        let center = self.walkable.map_or(player_position, |walkable| {
+            frame_within(player_position, walkable, half_size)
+        });
+
+        Rect::from_center_half_size(center, half_size)
+    }
+
+    pub fn view_size(&self) -> Vec2 {
+        self.view_size
+    }
+
+    pub fn set_view_size(&mut self, view_size: Vec2) {
+        self.view_size = view_size;
+    }
+
+    pub fn walkable(&self) -> Option<Rect> {
+        self.walkable
+    }
+
+    pub fn set_walkable(&mut self, walkable: Option<Rect>) {
+        self.walkable = walkable;
+    }
+}
+
+impl Default for SpawnArea {
+    fn default() -> Self {
+        Self::new(VIEW_SIZE, None)
+    }
+}
+
+/// Everything needed to pick a spot for an enemy and put it there.
+#[derive(SystemParam)]
+pub struct EnemySpawner<'w, 's> {
+    pub commands: Commands<'w, 's>,
+    pub archetypes: Res<'w, EnemyArchetypes>,
+    pub spawn_area: Res<'w, SpawnArea>,
+    pub rng: ResMut<'w, RunRng>,
+    pub pool: ResMut<'w, EnemyPool>,
+    pub player_query: Query<'w, 's, &'static Transform, With<Player>>,
+    pub collider_query: Query<'w, 's, (&'static Collider, &'static Transform), Without<Projectile>>,
+}
+
+impl EnemySpawner<'_, '_> {
+    /// Where every collider is, spawns add to it so they don't land on each other.
+    pub fn occupied(&self) -> Vec<(Vec3, f32)> {
+        self.collider_query
+            .iter()
+            .map(|(collider, transform)| (transform.translation, collider.radius()))
+            .collect()
+    }
+}
+
+/// Follows the camera settings, see [`max_view_size`].
+pub fn sync_spawn_view(settings: Res<Settings>, mut spawn_area: ResMut<SpawnArea>) {
+    let view_size = max_view_size(&settings);
+
+    if spawn_area.view_size() != view_size {
+        spawn_area.set_view_size(view_size);
+    }
+}
+
+fn random_unit(rng: &mut RunRng) -> f32 {
+    rng.stream(RngStream::Spawning).next_u32() as f32 / u32::MAX as f32
+}
+
+/// Walks `distance` of the way around the rectangle's edge, starting at its bottom left corner.
+fn point_on_perimeter(rect: Rect, distance: f32) -> Vec2 {
+    let size = rect.size();
+    let mut remaining = distance * 2.0 * (size.x + size.y);
+
+    if remaining < size.x {
+        return Vec2::new(rect.min.x + remaining, rect.min.y);
+    }
+    remaining -= size.x;
+
+    if remaining < size.y {
+        return Vec2::new(rect.max.x, rect.min.y + remaining);
+    }
+    remaining -= size.y;
+
+    if remaining < size.x {
+        return Vec2::new(rect.max.x - remaining, rect.max.y);
+    }
+    remaining -= size.x;
+
+    Vec2::new(rect.min.x, rect.max.y - remaining)
+}
+
+/// Whether any of the rectangle's edge lies within `area`.
+fn perimeter_crosses(rect: Rect, area: Rect) -> bool {
+    let inside = rect.min.cmplt(area.min).all() && rect.max.cmpgt(area.max).all();
+
+    !rect.intersect(area).is_empty() && !inside
+}
+
+/// The side of `area` furthest from `point`, as its two ends.
+fn farthest_edge(area: Rect, point: Vec2) -> (Vec2, Vec2) {
+    let top_left = Vec2::new(area.min.x, area.max.y);
+    let bottom_right = Vec2::new(area.max.x, area.min.y);
+
+    [
+        (point.x - area.min.x, area.min, top_left),
+        (area.max.x - point.x, bottom_right, area.max),
+        (point.y - area.min.y, area.min, bottom_right),
+        (area.max.y - point.y, top_left, area.max),
+    ]
+    .into_iter()
+    .max_by(|a, b| a.0.total_cmp(&b.0))
+    .map(|(_, start, end)| (start, end))
+    .expect("a rectangle has four sides")
+}
+
+/// Whether a collider of `radius` at `candidate` is fully on walkable ground and clear of
+/// everything in `occupied`.
+fn is_free(spawn_area: &SpawnArea, radius: f32, candidate: Vec2, occupied: &[(Vec3, f32)]) -> bool {
+    let on_walkable = spawn_area.walkable().is_none_or(|walkable| {
+        let inner = walkable.inset(-radius);
+
+        !inner.is_empty() && inner.contains(candidate)
+    });
+
+    let clear = occupied.iter().all(|(position, occupied_radius)| {
+        candidate.distance(position.truncate()) >= radius + occupied_radius
+    });
+
+    on_walkable && clear
+}
+
+/// Picks somewhere for an enemy of `archetype` to appear that follows its spawn rules: just
+/// outside the view if it must be off screen, fully on walkable ground, clear of everything in
+/// `occupied` (positions and collider radii) and far enough from the player. `None` if nowhere
+/// fitting turned up this time.
+///
+/// When none of the ring just outside the view is walkable, e.g. on a map smaller than the view,
+/// off screen enemies come in along the edge of the walkable area furthest from the player.
+pub fn find_spawn_position(
+    rng: &mut RunRng,
+    spawn_area: &SpawnArea,
+    archetype: &EnemyArchetype,
+    player_position: Vec3,
+    occupied: &[(Vec3, f32)],
+) -> Option<Vec3> {
+    let rules = archetype.spawn();
+    let radius = archetype.collider_radius();
+    let view = spawn_area.view(player_position.truncate());
+    let inner = spawn_area
+        .walkable()
+        .map(|walkable| walkable.inset(-radius));
+
+    let fits = |candidate: Vec2| {
+        let far_enough =
+            candidate.distance(player_position.truncate()) >= rules.min_player_distance();
+
+        far_enough && is_free(spawn_area, radius, candidate, occupied)
+    };
+
+    let ring = Rect::from_center_half_size(
+        view.center(),
+        view.half_size() + Vec2::splat(radius + SPAWN_MARGIN),
+    );
+
+    let edge = inner
+        .filter(|inner| rules.off_screen() && !inner.is_empty() && !perimeter_crosses(ring, *inner))
+        .map(|inner| farthest_edge(inner, player_position.truncate()));
+
+    for _ in 0..SPAWN_ATTEMPTS {
+        let candidate = match edge {
+            Some((start, end)) => start.lerp(end, random_unit(rng)),
+            None if rules.off_screen() => point_on_perimeter(ring, random_unit(rng)),
+            None => {
+                let area = spawn_area.walkable().unwrap_or(view);
+
+                area.min + area.size() * Vec2::new(random_unit(rng), random_unit(rng))
+            }
+        };
+
+        if fits(candidate) {
+            return Some(candidate.extend(player_position.z));
+        }
+    }
+
+    None
+}
+
+/// Like [`find_spawn_position`] but never gives up, for spawns that can't wait such as bosses.
+/// When nowhere follows the spawn rules it settles for anywhere walkable and clear that's far
+/// enough from the player, on screen or not, and failing that the walkable corner furthest from
+/// the player.
+pub fn force_spawn_position(
+    rng: &mut RunRng,
+    spawn_area: &SpawnArea,
+    archetype: &EnemyArchetype,
+    player_position: Vec3,
+    occupied: &[(Vec3, f32)],
+) -> Vec3 {
+    if let Some(position) =
+        find_spawn_position(rng, spawn_area, archetype, player_position, occupied)
+    {
+        return position;
+    }
+
+    let player = player_position.truncate();
+    let radius = archetype.collider_radius();
+    let area = spawn_area
+        .walkable()
+        .map(|walkable| walkable.inset(-radius))
+        .filter(|inner| !inner.is_empty())
+        .unwrap_or_else(|| spawn_area.view(player));
+
+    for _ in 0..SPAWN_ATTEMPTS {
+        let candidate = area.min + area.size() * Vec2::new(random_unit(rng), random_unit(rng));
+
+        if candidate.distance(player) >= archetype.spawn().min_player_distance()
+            && is_free(spawn_area, radius, candidate, occupied)
+        {
+            return candidate.extend(player_position.z);
+        }
+    }
+
+    [
+        area.min,
+        area.max,
+        Vec2::new(area.min.x, area.max.y),
+        Vec2::new(area.max.x, area.min.y),
+    ]
+    .into_iter()
+    .max_by(|a, b| {
+        a.distance_squared(player)
+            .total_cmp(&b.distance_squared(player))
+    })
+    .expect("a rectangle has four corners")
+    .extend(player_position.z)
+}
+
+/// Somewhere on walkable ground `distance` from `center` and clear of everything in `occupied`,
+/// for enemies that appear next to another rather than by their spawn rules, like a boss's
+/// minions. `angle` is tried first, then random ones. `None` if nowhere fitting turned up.
+pub fn find_position_around(
+    rng: &mut RunRng,
+    spawn_area: &SpawnArea,
+    radius: f32,
+    center: Vec3,
+    distance: f32,
+    angle: f32,
+    occupied: &[(Vec3, f32)],
+) -> Option<Vec3> {
+    let angles = iter::once(angle)
+        .chain(iter::repeat_with(|| TAU * random_unit(rng)))
+        .take(SPAWN_ATTEMPTS as usize);
+
+    for angle in angles {
+        let candidate = center.truncate() + Vec2::from_angle(angle) * distance;
+
+        if is_free(spawn_area, radius, candidate, occupied) {
+            return Some(candidate.extend(center.z));
+        }
+    }
+
+    None
+}
@@ -0,0 +1,71 @@
+use bevy::prelude::*;
+
+use crate::app::AppState;
+
+use super::{despawn_all, rng::RunRng};
+
+#[derive(Component)]
+struct MainMenuUi;
+
+#[derive(Component)]
+struct PausedUi;
+
+#[derive(Component)]
+struct GameOverUi;
+
+fn spawn_overlay<T: Component>(commands: &mut Commands, marker: T, text: &str) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.),
+                    height: Val::Percent(100.),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                ..default()
+            },
+            marker,
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                text,
+                TextStyle {
+                    font_size: 32.,
+                    color: Color::WHITE,
+                    ..default()
+                },
+            ));
+        });
+}
+
+fn spawn_main_menu(mut commands: Commands) {
+    spawn_overlay(&mut commands, MainMenuUi, "Press Enter to start");
+}
+
+fn spawn_paused(mut commands: Commands) {
+    spawn_overlay(&mut commands, PausedUi, "Paused");
+}
+
+fn spawn_game_over(mut commands: Commands, rng: Option<Res<RunRng>>) {
+    let text = match rng {
+        Some(rng) => format!("Game over - seed {} - press Enter to restart", rng.seed()),
+        None => "Game over - press Enter to restart".to_owned(),
+    };
+
+    spawn_overlay(&mut commands, GameOverUi, &text);
+}
+
+pub struct GameUiPlugin;
+
+impl Plugin for GameUiPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(AppState::MainMenu), spawn_main_menu);
+        app.add_systems(OnExit(AppState::MainMenu), despawn_all::<MainMenuUi>);
+        app.add_systems(OnEnter(AppState::Paused), spawn_paused);
+        app.add_systems(OnExit(AppState::Paused), despawn_all::<PausedUi>);
+        app.add_systems(OnEnter(AppState::GameOver), spawn_game_over);
+        app.add_systems(OnExit(AppState::GameOver), despawn_all::<GameOverUi>);
+    }
+}
@@ -0,0 +1,551 @@
+use bevy::{
+    asset::{AssetLoader, LoadContext, LoadedAsset},
+    prelude::*,
+    reflect::{TypePath, TypeUuid},
+    utils::BoxedFuture,
+};
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::app::AppState;
+
+use super::{
+    boss::spawn_boss,
+    clock::SimulationClock,
+    enemy::{spawn_enemy, steer_enemies, Enemy},
+    physics::InterpolatedPosition,
+    player::Player,
+    rng::RunRng,
+    spawn::{find_spawn_position, force_spawn_position, sync_spawn_view, EnemySpawner, SpawnArea},
+    BossArrived, GameSet, WaveCleared, WaveStarted,
+};
+
+const WAVE_SCHEDULE_PATH: &str = "run.waves.ron";
+
+#[derive(Error, Debug)]
+pub enum WaveScheduleError {
+    #[error("could not parse wave schedule: {0}")]
+    Parse(#[from] ron::error::SpannedError),
+}
+
+/// One archetype's share of a wave, spawned one at a time every `interval` seconds starting
+/// `delay` seconds into the wave.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct WaveGroup {
+    archetype: String,
+    count: u32,
+    interval: f32,
+    #[serde(default)]
+    delay: f32,
+}
+
+impl WaveGroup {
+    pub fn archetype(&self) -> &str {
+        &self.archetype
+    }
+
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+
+    pub fn interval(&self) -> f32 {
+        self.interval
+    }
+
+    pub fn delay(&self) -> f32 {
+        self.delay
+    }
+
+    /// Seconds into the wave that the enemy at `index` is due.
+    fn spawn_time(&self, index: u32) -> f32 {
+        self.delay + self.interval * index as f32
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct WaveDefinition {
+    groups: Vec<WaveGroup>,
+    rest: f32,
+}
+
+impl WaveDefinition {
+    pub fn groups(&self) -> &[WaveGroup] {
+        &self.groups
+    }
+
+    /// Seconds of calm after the wave is cleared.
+    pub fn rest(&self) -> f32 {
+        self.rest
+    }
+}
+
+/// Limits on the living enemies, optional in the schedule file.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct EnemyPopulation {
+    max_enemies: u32,
+    relocate_margin: f32,
+}
+
+impl EnemyPopulation {
+    /// Spawning pauses while this many enemies are alive, picking up again as they die.
+    pub fn max_enemies(&self) -> u32 {
+        self.max_enemies
+    }
+
+    /// Enemies further than this outside the most the camera can show are moved back to just off
+    /// screen.
+    pub fn relocate_margin(&self) -> f32 {
+        self.relocate_margin
+    }
+}
+
+impl Default for EnemyPopulation {
+    fn default() -> Self {
+        Self {
+            max_enemies: 60,
+            relocate_margin: 192.0,
+        }
+    }
+}
+
+/// When a boss turns up during a run.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub enum BossTrigger {
+    /// As this wave starts, which then isn't cleared until the boss is dead too.
+    Wave(u32),
+    /// This many seconds into the run, whichever wave is on.
+    Time(f32),
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct BossEncounter {
+    archetype: String,
+    trigger: BossTrigger,
+}
+
+impl BossEncounter {
+    pub fn archetype(&self) -> &str {
+        &self.archetype
+    }
+
+    pub fn trigger(&self) -> BossTrigger {
+        self.trigger
+    }
+}
+
+/// Every wave of a run, loaded from `assets/run.waves.ron`. Once the listed waves run out the last
+/// one repeats, growing by `overtime_growth` each time.
+#[derive(Resource, Debug, Clone, Default, PartialEq, Deserialize, TypeUuid, TypePath)]
+#[uuid = "0f6b3c8e-7d2a-4e91-b5c4-2a9d81e6f03b"]
+pub struct WaveSchedule {
+    opening_rest: f32,
+    overtime_growth: f32,
+    #[serde(default)]
+    population: EnemyPopulation,
+    waves: Vec<WaveDefinition>,
+    #[serde(default)]
+    bosses: Vec<BossEncounter>,
+}
+
+impl WaveSchedule {
+    pub fn from_ron(ron: &str) -> Result<Self, WaveScheduleError> {
+        Ok(ron::from_str(ron)?)
+    }
+
+    /// Only until the schedule file has loaded, a schedule without waves has nothing to run.
+    pub fn is_empty(&self) -> bool {
+        self.waves.is_empty()
+    }
+
+    /// Seconds before the first wave.
+    pub fn opening_rest(&self) -> f32 {
+        self.opening_rest
+    }
+
+    pub fn population(&self) -> &EnemyPopulation {
+        &self.population
+    }
+
+    /// Each one appears once per run.
+    pub fn bosses(&self) -> &[BossEncounter] {
+        &self.bosses
+    }
+
+    /// Wave numbers start at 1. `None` only when the schedule has no waves at all.
+    pub fn wave(&self, number: u32) -> Option<WaveDefinition> {
+        let index = number.saturating_sub(1) as usize;
+
+        if let Some(wave) = self.waves.get(index) {
+            return Some(wave.clone());
+        }
+
+        let mut wave = self.waves.last()?.clone();
+        let overtime = (index + 1 - self.waves.len()) as i32;
+        let growth = (1.0 + self.overtime_growth.max(0.0)).powi(overtime);
+
+        for group in wave.groups.iter_mut() {
+            group.count = (group.count as f32 * growth).round() as u32;
+        }
+
+        Some(wave)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum WavePhase {
+    /// Waiting out the rest before the next wave.
+    Resting,
+    /// Groups are still spawning.
+    Spawning,
+    /// Everything has spawned, waiting for the player to finish it off.
+    Clearing,
+}
+
+/// Runs the current run's waves. Created on the run's first tick so the schedule has loaded.
+/// Times are simulation clock seconds since the phase began.
+#[derive(Resource, Debug)]
+pub struct WaveDirector {
+    wave: u32,
+    phase: WavePhase,
+    phase_elapsed: f32,
+    rest: f32,
+    spawned: Vec<u32>,
+    bosses_spawned: Vec<bool>,
+}
+
+impl WaveDirector {
+    pub fn new(opening_rest: f32) -> Self {
+        Self {
+            wave: 0,
+            phase: WavePhase::Resting,
+            phase_elapsed: 0.0,
+            rest: opening_rest,
+            spawned: Vec::new(),
+            bosses_spawned: Vec::new(),
+        }
+    }
+
+    /// The wave in progress, or the last one cleared while resting. 0 before the first wave.
+    pub fn wave(&self) -> u32 {
+        self.wave
+    }
+
+    pub fn is_resting(&self) -> bool {
+        self.phase == WavePhase::Resting
+    }
+
+    fn enter(&mut self, phase: WavePhase) {
+        self.phase = phase;
+        self.phase_elapsed = 0.0;
+    }
+}
+
+fn start_wave_director(mut commands: Commands, schedule: Res<WaveSchedule>) {
+    commands.insert_resource(WaveDirector::new(schedule.opening_rest()));
+}
+
+fn end_wave_director(mut commands: Commands) {
+    commands.remove_resource::<WaveDirector>();
+}
+
+fn direct_waves(
+    clock: Res<SimulationClock>,
+    schedule: Res<WaveSchedule>,
+    mut director: ResMut<WaveDirector>,
+    mut spawner: EnemySpawner,
+    enemy_query: Query<(), With<Enemy>>,
+    mut wave_started_events: EventWriter<WaveStarted>,
+    mut wave_cleared_events: EventWriter<WaveCleared>,
+) {
+    director.phase_elapsed += clock.delta_seconds();
+
+    match director.phase {
+        WavePhase::Resting => {
+            if director.phase_elapsed < director.rest {
+                return;
+            }
+
+            let Some(wave) = schedule.wave(director.wave + 1) else {
+                return;
+            };
+
+            director.wave += 1;
+            director.spawned = vec![0; wave.groups().len()];
+            director.enter(WavePhase::Spawning);
+
+            wave_started_events.send(WaveStarted::new(director.wave));
+        }
+        WavePhase::Spawning => {
+            let Some(wave) = schedule.wave(director.wave) else {
+                return;
+            };
+
+            let Ok(player_position) = spawner
+                .player_query
+                .get_single()
+                .map(|transform| transform.translation)
+            else {
+                return;
+            };
+
+            // A hot reloaded schedule can change the number of groups mid wave
+            director.spawned.resize(wave.groups().len(), 0);
+
+            // Enemies spawned this tick aren't in the world yet, so they're tracked here too
+            let mut occupied = spawner.occupied();
+
+            let mut alive = enemy_query.iter().count() as u32;
+
+            for (index, group) in wave.groups().iter().enumerate() {
+                while director.spawned[index] < group.count()
+                    && group.spawn_time(director.spawned[index]) <= director.phase_elapsed
+                {
+                    // At the cap the group falls behind and catches up as enemies die
+                    if alive >= schedule.population().max_enemies() {
+                        break;
+                    }
+
+                    let Some(archetype) = spawner.archetypes.get(group.archetype()) else {
+                        warn!("No enemy archetype with id {}", group.archetype());
+                        director.spawned[index] += 1;
+                        continue;
+                    };
+
+                    // Nowhere suitable right now, the group tries again next tick
+                    let Some(position) = find_spawn_position(
+                        &mut spawner.rng,
+                        &spawner.spawn_area,
+                        archetype,
+                        player_position,
+                        &occupied,
+                    ) else {
+                        break;
+                    };
+
+                    director.spawned[index] += 1;
+                    alive += 1;
+                    occupied.push((position, archetype.collider_radius()));
+                    spawn_enemy(
+                        &mut spawner.commands,
+                        &mut spawner.pool,
+                        archetype,
+                        position,
+                    );
+                }
+            }
+
+            let all_spawned = wave
+                .groups()
+                .iter()
+                .zip(director.spawned.iter())
+                .all(|(group, spawned)| *spawned >= group.count());
+
+            if all_spawned {
+                director.enter(WavePhase::Clearing);
+            }
+        }
+        WavePhase::Clearing => {
+            if !enemy_query.is_empty() {
+                return;
+            }
+
+            director.rest = schedule.wave(director.wave).map_or(0.0, |wave| wave.rest());
+            director.enter(WavePhase::Resting);
+
+            wave_cleared_events.send(WaveCleared::new(director.wave));
+        }
+    }
+}
+
+/// Bosses ignore the population cap and can't be held up, they turn up somewhere even when
+/// nowhere fits their spawn rules.
+pub fn direct_bosses(
+    clock: Res<SimulationClock>,
+    schedule: Res<WaveSchedule>,
+    mut director: ResMut<WaveDirector>,
+    mut spawner: EnemySpawner,
+    mut boss_arrived_events: EventWriter<BossArrived>,
+) {
+    let Ok(player_position) = spawner
+        .player_query
+        .get_single()
+        .map(|transform| transform.translation)
+    else {
+        return;
+    };
+
+    // A hot reloaded schedule can add or remove bosses mid run
+    director
+        .bosses_spawned
+        .resize(schedule.bosses().len(), false);
+
+    let mut occupied = spawner.occupied();
+
+    for (index, encounter) in schedule.bosses().iter().enumerate() {
+        if director.bosses_spawned[index] {
+            continue;
+        }
+
+        let due = match encounter.trigger() {
+            BossTrigger::Wave(wave) => director.wave() >= wave,
+            BossTrigger::Time(seconds) => clock.elapsed_seconds() >= seconds,
+        };
+
+        if !due {
+            continue;
+        }
+
+        director.bosses_spawned[index] = true;
+
+        let Some(archetype) = spawner.archetypes.get(encounter.archetype()) else {
+            warn!("No enemy archetype with id {}", encounter.archetype());
+            continue;
+        };
+
+        let position = force_spawn_position(
+            &mut spawner.rng,
+            &spawner.spawn_area,
+            archetype,
+            player_position,
+            &occupied,
+        );
+        occupied.push((position, archetype.collider_radius()));
+
+        let boss = spawn_boss(&mut spawner.commands, archetype, position);
+        boss_arrived_events.send(BossArrived::new(boss));
+    }
+}
+
+/// Enemies left far behind are brought back to just off screen rather than despawned, so they
+/// still count towards clearing the wave and don't need to walk all the way back.
+fn relocate_distant_enemies(
+    schedule: Res<WaveSchedule>,
+    spawn_area: Res<SpawnArea>,
+    mut rng: ResMut<RunRng>,
+    player_query: Query<&Transform, (With<Player>, Without<Enemy>)>,
+    mut enemy_query: Query<
+        (&Enemy, &mut Transform, Option<&mut InterpolatedPosition>),
+        Without<Player>,
+    >,
+) {
+    let Ok(player_transform) = player_query.get_single() else {
+        return;
+    };
+
+    let player_position = player_transform.translation;
+    let keep_area = spawn_area
+        .view(player_position.truncate())
+        .inset(schedule.population().relocate_margin());
+
+    let mut occupied: Vec<(Vec3, f32)> = enemy_query
+        .iter()
+        .map(|(enemy, transform, _)| (transform.translation, enemy.archetype().collider_radius()))
+        .collect();
+
+    for (enemy, mut transform, interpolated) in enemy_query.iter_mut() {
+        if keep_area.contains(transform.translation.truncate()) {
+            continue;
+        }
+
+        // Nowhere suitable right now, it tries again next tick
+        if let Some(position) = find_spawn_position(
+            &mut rng,
+            &spawn_area,
+            enemy.archetype(),
+            player_position,
+            &occupied,
+        ) {
+            transform.translation = position;
+
+            // A jump, not a move, so rendering shouldn't slide it across the screen
+            if let Some(mut interpolated) = interpolated {
+                *interpolated = InterpolatedPosition::new(position);
+            }
+
+            occupied.push((position, enemy.archetype().collider_radius()));
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct WaveScheduleLoader;
+
+impl AssetLoader for WaveScheduleLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let schedule = WaveSchedule::from_ron(std::str::from_utf8(bytes)?)?;
+            load_context.set_default_asset(LoadedAsset::new(schedule));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["waves.ron"]
+    }
+}
+
+#[derive(Resource)]
+struct WaveScheduleHandle(Handle<WaveSchedule>);
+
+fn load_wave_schedule(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(WaveScheduleHandle(asset_server.load(WAVE_SCHEDULE_PATH)));
+}
+
+fn sync_wave_schedule(
+    mut asset_events: EventReader<AssetEvent<WaveSchedule>>,
+    assets: Res<Assets<WaveSchedule>>,
+    handle: Res<WaveScheduleHandle>,
+    mut schedule: ResMut<WaveSchedule>,
+) {
+    if asset_events.is_empty() {
+        return;
+    }
+    asset_events.clear();
+
+    if let Some(loaded) = assets.get(&handle.0) {
+        *schedule = loaded.clone();
+    }
+}
+
+pub struct WaveDirectorPlugin;
+
+impl Plugin for WaveDirectorPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<WaveSchedule>();
+        app.init_resource::<SpawnArea>();
+        app.add_systems(
+            FixedUpdate,
+            (
+                sync_spawn_view,
+                start_wave_director.run_if(not(resource_exists::<WaveDirector>())),
+            )
+                .in_set(GameSet::BeginTick),
+        );
+        app.add_systems(OnEnter(AppState::GameOver), end_wave_director);
+        app.add_systems(
+            FixedUpdate,
+            (relocate_distant_enemies, direct_waves, direct_bosses)
+                .chain()
+                .before(steer_enemies)
+                .run_if(resource_exists::<WaveDirector>())
+                .run_if(resource_exists::<RunRng>())
+                .in_set(GameSet::Ai),
+        );
+    }
+}
+
+pub struct WaveScheduleAssetsPlugin;
+
+impl Plugin for WaveScheduleAssetsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_asset::<WaveSchedule>();
+        app.init_asset_loader::<WaveScheduleLoader>();
+        app.add_systems(Startup, load_wave_schedule);
+        app.add_systems(Update, sync_wave_schedule);
+    }
+}
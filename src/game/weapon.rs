@@ -1,21 +1,24 @@
-use std::time::{Duration, Instant};
+use std::time::Duration;
 
 use bevy::prelude::*;
 
 use super::{
     physics::{Collider, Velocity},
-    projectile::{Projectile, ProjectileBundle},
+    projectile::{Projectile, ProjectileBundle, ProjectileRange},
     EnitityAllegence,
 };
 
 const AXE_VELOCITY: f32 = 512.;
+const AXE_RANGE: f32 = 640.;
 
+/// Attack times are simulation clock seconds, so cooldowns freeze while paused and stretch
+/// with the time scale.
 #[derive(Component)]
 pub enum PlayerWeapon {
-    Axe { last_attack: Option<Instant> },
+    Axe { last_attack: Option<f32> },
 }
 
-fn spawn_axe(mut commands: Commands, player_transform: Vec3, player_facing: Vec3) {
+fn spawn_axe(commands: &mut Commands, player_transform: Vec3, player_facing: Vec3) {
     let mut transform = Transform::from_translation(Vec3::new(0.0, 0.0, 0.0));
     transform.rotate(Quat::from_rotation_z(player_facing.angle_between(Vec3::X)));
     transform.translation += player_transform + player_facing * 0.5;
@@ -28,6 +31,7 @@ fn spawn_axe(mut commands: Commands, player_transform: Vec3, player_facing: Vec3
         collider,
         projectile: Projectile::new(25),
         allegence: EnitityAllegence::Player,
+        range: ProjectileRange::new(player_transform, AXE_RANGE),
     });
 }
 
@@ -36,8 +40,14 @@ impl PlayerWeapon {
         Self::Axe { last_attack: None }
     }
 
-    pub fn attack(&mut self, commands: Commands, player_transform: Vec3, player_facing: Vec3) {
-        if !self.can_attack() {
+    pub fn attack(
+        &mut self,
+        commands: &mut Commands,
+        time: f32,
+        player_transform: Vec3,
+        player_facing: Vec3,
+    ) {
+        if !self.can_attack(time) {
             return;
         }
 
@@ -46,7 +56,7 @@ impl PlayerWeapon {
         }
 
         match self {
-            Self::Axe { last_attack } => *last_attack = Some(Instant::now()),
+            Self::Axe { last_attack } => *last_attack = Some(time),
         }
     }
 
@@ -56,14 +66,15 @@ impl PlayerWeapon {
         }
     }
 
-    fn cooldown_remaining(&self) -> Duration {
+    fn cooldown_remaining(&self, time: f32) -> Duration {
         let last_attack = match self {
             Self::Axe { last_attack } => last_attack,
         };
 
         match last_attack {
             Some(last_attack) => {
-                let time_since_last_attack = Instant::now().duration_since(*last_attack);
+                let time_since_last_attack =
+                    Duration::from_secs_f32((time - last_attack).max(0.0));
                 self.cooldown()
                     .checked_sub(time_since_last_attack)
                     .unwrap_or_default()
@@ -72,8 +83,15 @@ impl PlayerWeapon {
         }
     }
 
-    pub fn can_attack(&self) -> bool {
-        self.cooldown_remaining() <= Duration::from_secs(0)
+    pub fn can_attack(&self, time: f32) -> bool {
+        self.cooldown_remaining(time) <= Duration::from_secs(0)
+    }
+
+    /// How hard firing kicks the camera, 0 for light weapons up to 1 for the heaviest.
+    pub fn recoil(&self) -> f32 {
+        match self {
+            Self::Axe { .. } => 0.0,
+        }
     }
 
     fn damage(&self) -> u32 {
@@ -0,0 +1,409 @@
+use std::collections::BTreeMap;
+
+use bevy::{ecs::system::SystemParam, input::InputSystem, prelude::*, window::CursorMoved};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::settings::Settings;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum InputAction {
+    MoveUp,
+    MoveDown,
+    MoveLeft,
+    MoveRight,
+    FirePrimary,
+    FireSecondary,
+    Pause,
+    Confirm,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum InputBinding {
+    Key(KeyCode),
+    Mouse(MouseButton),
+    Gamepad(GamepadButtonType),
+}
+
+impl InputBinding {
+    fn pressed(
+        &self,
+        keyboard_input: &Input<KeyCode>,
+        mouse_input: &Input<MouseButton>,
+        gamepads: &Gamepads,
+        gamepad_input: &Input<GamepadButton>,
+    ) -> bool {
+        match self {
+            Self::Key(key) => keyboard_input.pressed(*key),
+            Self::Mouse(button) => mouse_input.pressed(*button),
+            Self::Gamepad(button) => gamepads
+                .iter()
+                .any(|gamepad| gamepad_input.pressed(GamepadButton::new(gamepad, *button))),
+        }
+    }
+
+    /// Keyboard and mouse bindings count as one device, like [`InputDevice::KeyboardMouse`].
+    fn is_gamepad(&self) -> bool {
+        matches!(self, Self::Gamepad(_))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum InputDevice {
+    #[default]
+    KeyboardMouse,
+    Gamepad(Gamepad),
+}
+
+/// The device the player touched most recently, used to pick between mouse and stick aiming.
+#[derive(Resource, Debug, Default)]
+pub struct ActiveInputDevice(InputDevice);
+
+impl ActiveInputDevice {
+    pub fn get(&self) -> InputDevice {
+        self.0
+    }
+}
+
+/// Stick positions of the active gamepad with the configured dead zones applied.
+#[derive(Resource, Debug, Default)]
+pub struct GamepadSticks {
+    movement: Vec2,
+    aim: Vec2,
+}
+
+impl GamepadSticks {
+    pub fn movement(&self) -> Vec2 {
+        self.movement
+    }
+
+    pub fn aim(&self) -> Vec2 {
+        self.aim
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GamepadDeadZones {
+    movement: f32,
+    aim: f32,
+}
+
+impl GamepadDeadZones {
+    pub fn new(movement: f32, aim: f32) -> Self {
+        Self { movement, aim }
+    }
+
+    pub fn movement(&self) -> f32 {
+        self.movement
+    }
+
+    pub fn aim(&self) -> f32 {
+        self.aim
+    }
+}
+
+impl Default for GamepadDeadZones {
+    fn default() -> Self {
+        Self::new(0.15, 0.25)
+    }
+}
+
+/// Radial dead zone, rescaled so the stick still reaches full length at its edge.
+fn apply_dead_zone(stick: Vec2, dead_zone: f32) -> Vec2 {
+    let length = stick.length();
+
+    if length <= dead_zone || dead_zone >= 1.0 {
+        return Vec2::ZERO;
+    }
+
+    let scaled_length = ((length - dead_zone) / (1.0 - dead_zone)).min(1.0);
+
+    stick / length * scaled_length
+}
+
+fn read_stick(
+    axes: &Axis<GamepadAxis>,
+    gamepad: Gamepad,
+    x: GamepadAxisType,
+    y: GamepadAxisType,
+) -> Vec2 {
+    Vec2::new(
+        axes.get(GamepadAxis::new(gamepad, x)).unwrap_or_default(),
+        axes.get(GamepadAxis::new(gamepad, y)).unwrap_or_default(),
+    )
+}
+
+impl GamepadSticks {
+    fn read(axes: &Axis<GamepadAxis>, gamepad: Gamepad, dead_zones: &GamepadDeadZones) -> Self {
+        let movement = read_stick(
+            axes,
+            gamepad,
+            GamepadAxisType::LeftStickX,
+            GamepadAxisType::LeftStickY,
+        );
+        let aim = read_stick(
+            axes,
+            gamepad,
+            GamepadAxisType::RightStickX,
+            GamepadAxisType::RightStickY,
+        );
+
+        Self {
+            movement: apply_dead_zone(movement, dead_zones.movement()),
+            aim: apply_dead_zone(aim, dead_zones.aim()),
+        }
+    }
+
+    fn is_neutral(&self) -> bool {
+        self.movement == Vec2::ZERO && self.aim == Vec2::ZERO
+    }
+}
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum InputMapError {
+    #[error("{binding:?} is already bound to {action:?}")]
+    Conflict {
+        binding: InputBinding,
+        action: InputAction,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InputMap {
+    bindings: BTreeMap<InputAction, Vec<InputBinding>>,
+}
+
+impl InputMap {
+    pub fn empty() -> Self {
+        Self {
+            bindings: BTreeMap::new(),
+        }
+    }
+
+    pub fn bindings(&self, action: InputAction) -> &[InputBinding] {
+        self.bindings
+            .get(&action)
+            .map(|bindings| bindings.as_slice())
+            .unwrap_or_default()
+    }
+
+    pub fn action_for(&self, binding: InputBinding) -> Option<InputAction> {
+        self.bindings
+            .iter()
+            .find(|(_, bindings)| bindings.contains(&binding))
+            .map(|(action, _)| *action)
+    }
+
+    pub fn bind(&mut self, action: InputAction, binding: InputBinding) -> Result<(), InputMapError> {
+        match self.action_for(binding) {
+            Some(existing) if existing == action => Ok(()),
+            Some(existing) => Err(InputMapError::Conflict {
+                binding,
+                action: existing,
+            }),
+            None => {
+                self.bindings.entry(action).or_default().push(binding);
+                Ok(())
+            }
+        }
+    }
+
+    pub fn unbind(&mut self, action: InputAction, binding: InputBinding) {
+        if let Some(bindings) = self.bindings.get_mut(&action) {
+            bindings.retain(|bound| *bound != binding);
+        }
+    }
+
+    /// Gives actions missing from the map their default bindings, so actions added after a
+    /// settings file was written still do something. Defaults that conflict are skipped.
+    pub fn bind_missing_defaults(&mut self) {
+        let defaults = Self::default();
+
+        for (action, bindings) in defaults.bindings {
+            if self.bindings.contains_key(&action) {
+                continue;
+            }
+
+            for binding in bindings {
+                let _ = self.bind(action, binding);
+            }
+        }
+    }
+
+    /// Gives each action the default bindings for any device it has nothing bound on, e.g.
+    /// gamepad bindings for files written before gamepads were supported. Defaults that conflict
+    /// are skipped.
+    pub fn bind_missing_device_defaults(&mut self) {
+        let defaults = Self::default();
+
+        for (action, bindings) in defaults.bindings {
+            let bound = self.bindings(action);
+            let has_gamepad = bound.iter().any(|binding| binding.is_gamepad());
+            let has_keyboard_mouse = bound.iter().any(|binding| !binding.is_gamepad());
+
+            for binding in bindings {
+                let device_bound = if binding.is_gamepad() {
+                    has_gamepad
+                } else {
+                    has_keyboard_mouse
+                };
+
+                if !device_bound {
+                    let _ = self.bind(action, binding);
+                }
+            }
+        }
+    }
+
+    /// Bindings that trigger more than one action, only possible in a hand edited file.
+    pub fn conflicts(&self) -> Vec<InputMapError> {
+        let mut conflicts = vec![];
+
+        for (action, bindings) in self.bindings.iter() {
+            for binding in bindings {
+                if let Some(existing) = self.action_for(*binding) {
+                    if existing != *action {
+                        conflicts.push(InputMapError::Conflict {
+                            binding: *binding,
+                            action: existing,
+                        });
+                    }
+                }
+            }
+        }
+
+        conflicts
+    }
+}
+
+impl Default for InputMap {
+    fn default() -> Self {
+        let defaults = [
+            (InputAction::MoveUp, InputBinding::Key(KeyCode::W)),
+            (InputAction::MoveUp, InputBinding::Key(KeyCode::Up)),
+            (InputAction::MoveDown, InputBinding::Key(KeyCode::S)),
+            (InputAction::MoveDown, InputBinding::Key(KeyCode::Down)),
+            (InputAction::MoveLeft, InputBinding::Key(KeyCode::A)),
+            (InputAction::MoveLeft, InputBinding::Key(KeyCode::Left)),
+            (InputAction::MoveRight, InputBinding::Key(KeyCode::D)),
+            (InputAction::MoveRight, InputBinding::Key(KeyCode::Right)),
+            (InputAction::FirePrimary, InputBinding::Mouse(MouseButton::Left)),
+            (
+                InputAction::FirePrimary,
+                InputBinding::Gamepad(GamepadButtonType::RightTrigger2),
+            ),
+            (InputAction::FireSecondary, InputBinding::Mouse(MouseButton::Right)),
+            (
+                InputAction::FireSecondary,
+                InputBinding::Gamepad(GamepadButtonType::LeftTrigger2),
+            ),
+            (InputAction::Pause, InputBinding::Key(KeyCode::P)),
+            (InputAction::Pause, InputBinding::Gamepad(GamepadButtonType::Start)),
+            (InputAction::Confirm, InputBinding::Key(KeyCode::Return)),
+            (InputAction::Confirm, InputBinding::Key(KeyCode::Space)),
+            (InputAction::Confirm, InputBinding::Gamepad(GamepadButtonType::South)),
+        ];
+
+        let mut input_map = Self::empty();
+
+        for (action, binding) in defaults {
+            input_map
+                .bind(action, binding)
+                .expect("default bindings should not conflict");
+        }
+
+        input_map
+    }
+}
+
+fn update_action_input(
+    settings: Res<Settings>,
+    keyboard_input: Res<Input<KeyCode>>,
+    mouse_input: Res<Input<MouseButton>>,
+    gamepads: Res<Gamepads>,
+    gamepad_input: Res<Input<GamepadButton>>,
+    mut action_input: ResMut<Input<InputAction>>,
+) {
+    action_input.clear();
+
+    for (action, bindings) in settings.input_map().bindings.iter() {
+        if bindings.iter().any(|binding| {
+            binding.pressed(&keyboard_input, &mouse_input, &gamepads, &gamepad_input)
+        }) {
+            action_input.press(*action);
+        } else {
+            action_input.release(*action);
+        }
+    }
+}
+
+/// Keyboard and mouse input this frame, to tell whether the player just used them.
+#[derive(SystemParam)]
+struct KeyboardMouseActivity<'w, 's> {
+    keyboard_input: Res<'w, Input<KeyCode>>,
+    mouse_input: Res<'w, Input<MouseButton>>,
+    cursor_moved_events: EventReader<'w, 's, CursorMoved>,
+}
+
+impl KeyboardMouseActivity<'_, '_> {
+    fn used(&mut self) -> bool {
+        self.keyboard_input.get_just_pressed().len() > 0
+            || self.mouse_input.get_just_pressed().len() > 0
+            || self.cursor_moved_events.iter().count() > 0
+    }
+}
+
+fn update_active_input_device(
+    settings: Res<Settings>,
+    mut keyboard_mouse: KeyboardMouseActivity,
+    gamepads: Res<Gamepads>,
+    gamepad_input: Res<Input<GamepadButton>>,
+    axes: Res<Axis<GamepadAxis>>,
+    mut active_device: ResMut<ActiveInputDevice>,
+    mut sticks: ResMut<GamepadSticks>,
+) {
+    let dead_zones = settings.gamepad_dead_zones();
+
+    if let Some(button) = gamepad_input.get_just_pressed().next() {
+        active_device.0 = InputDevice::Gamepad(button.gamepad);
+    }
+
+    for gamepad in gamepads.iter() {
+        if !GamepadSticks::read(&axes, gamepad, dead_zones).is_neutral() {
+            active_device.0 = InputDevice::Gamepad(gamepad);
+        }
+    }
+
+    if keyboard_mouse.used() {
+        active_device.0 = InputDevice::KeyboardMouse;
+    }
+
+    *sticks = match active_device.get() {
+        InputDevice::Gamepad(gamepad) if gamepads.contains(gamepad) => {
+            GamepadSticks::read(&axes, gamepad, dead_zones)
+        }
+        _ => GamepadSticks::default(),
+    };
+}
+
+fn warn_about_conflicts(settings: Res<Settings>) {
+    for conflict in settings.input_map().conflicts() {
+        warn!("Input binding conflict: {conflict}");
+    }
+}
+
+pub struct ActionInputPlugin;
+
+impl Plugin for ActionInputPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Input<InputAction>>();
+        app.init_resource::<ActiveInputDevice>();
+        app.init_resource::<GamepadSticks>();
+        app.add_systems(Startup, warn_about_conflicts);
+        app.add_systems(
+            PreUpdate,
+            (update_action_input, update_active_input_device).after(InputSystem),
+        );
+    }
+}
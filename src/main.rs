@@ -1,5 +1,7 @@
+use std::time::Duration;
+
 use app::AppPlugin;
-use bevy::{prelude::*, window::close_on_esc};
+use bevy::{asset::ChangeWatcher, prelude::*, window::close_on_esc};
 use bevy_aseprite::AsepritePlugin;
 use bevy_prng::ChaCha8Rng;
 use bevy_rand::prelude::EntropyPlugin;
@@ -7,6 +9,8 @@ use game::GamePlugin;
 
 mod app;
 mod game;
+mod input;
+mod settings;
 
 fn main() {
     App::new()
@@ -19,7 +23,16 @@ fn main() {
                     }),
                     ..Default::default()
                 })
-                .set(ImagePlugin::default_nearest()),
+                .set(ImagePlugin::default_nearest())
+                .set(AssetPlugin {
+                    // Hot reload data files like enemy archetypes while developing
+                    watch_for_changes: if cfg!(debug_assertions) {
+                        ChangeWatcher::with_delay(Duration::from_millis(200))
+                    } else {
+                        None
+                    },
+                    ..Default::default()
+                }),
         )
         .insert_resource(ClearColor(Color::rgb(0.04, 0.04, 0.04)))
         .add_plugins((
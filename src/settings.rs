@@ -0,0 +1,306 @@
+use std::{fs, ops::RangeInclusive, path::PathBuf};
+
+use bevy::prelude::*;
+use ron::ser::PrettyConfig;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+    game::camera::{CameraSmoothing, CameraZoomLimits, GameCameraFollowMode},
+    input::{GamepadDeadZones, InputMap},
+};
+
+/// Bump whenever a field changes meaning, and teach [`Settings::migrate`] how to upgrade.
+/// Purely additive fields don't need a bump, missing fields fall back to their defaults.
+const SETTINGS_VERSION: u32 = 2;
+
+/// Simulation ticks per second the game accepts, so a hand edited file can't stall or flood the
+/// loop.
+pub const SIMULATION_TICK_RATES: RangeInclusive<f32> = 10.0..=240.0;
+
+const SETTINGS_DIRECTORY: &str = "blahbarian";
+const SETTINGS_FILE: &str = "settings.ron";
+
+#[derive(Error, Debug)]
+pub enum SettingsError {
+    #[error("could not find a config directory for this platform")]
+    NoConfigDirectory,
+    #[error("could not access settings file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("could not parse settings file: {0}")]
+    Parse(#[from] ron::error::SpannedError),
+    #[error("could not serialize settings: {0}")]
+    Serialize(#[from] ron::Error),
+}
+
+#[derive(Resource, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    // Files written before versioning have no version field, so treat them as version 0
+    #[serde(default)]
+    version: u32,
+    camera_follow_mode: GameCameraFollowMode,
+    camera_smoothing: CameraSmoothing,
+    camera_zoom_limits: CameraZoomLimits,
+    input_map: InputMap,
+    gamepad_dead_zones: GamepadDeadZones,
+    simulation_tick_rate: f32,
+    seed: Option<u64>,
+    camera_shake: f32,
+}
+
+impl Settings {
+    pub fn new() -> Self {
+        Self {
+            version: SETTINGS_VERSION,
+            camera_follow_mode: GameCameraFollowMode::Sticky,
+            camera_smoothing: CameraSmoothing::default(),
+            camera_zoom_limits: CameraZoomLimits::default(),
+            input_map: InputMap::default(),
+            gamepad_dead_zones: GamepadDeadZones::default(),
+            simulation_tick_rate: 60.0,
+            seed: None,
+            camera_shake: 1.0,
+        }
+    }
+
+    pub fn path() -> Result<PathBuf, SettingsError> {
+        dirs::config_dir()
+            .map(|dir| dir.join(SETTINGS_DIRECTORY).join(SETTINGS_FILE))
+            .ok_or(SettingsError::NoConfigDirectory)
+    }
+
+    pub fn from_ron(ron: &str) -> Result<Self, SettingsError> {
+        let settings: Self = ron::from_str(ron)?;
+
+        Ok(settings.migrate())
+    }
+
+    pub fn to_ron(&self) -> Result<String, SettingsError> {
+        Ok(ron::ser::to_string_pretty(self, PrettyConfig::default())?)
+    }
+
+    pub fn load() -> Result<Self, SettingsError> {
+        Self::from_ron(&fs::read_to_string(Self::path()?)?)
+    }
+
+    pub fn save(&self) -> Result<(), SettingsError> {
+        let path = Self::path()?;
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        fs::write(path, self.to_ron()?)?;
+
+        Ok(())
+    }
+
+    fn migrate(mut self) -> Self {
+        // Version 1 and older files were written before gamepad support, so every action is
+        // already there but only has keyboard and mouse bindings
+        if self.version < 2 {
+            self.input_map.bind_missing_device_defaults();
+        }
+
+        if self.version < SETTINGS_VERSION {
+            self.version = SETTINGS_VERSION;
+        }
+
+        self.input_map.bind_missing_defaults();
+
+        self
+    }
+
+    pub fn camera_follow_mode(&self) -> &GameCameraFollowMode {
+        &self.camera_follow_mode
+    }
+
+    pub fn set_camera_follow_mode(&mut self, camera_follow_mode: GameCameraFollowMode) {
+        self.camera_follow_mode = camera_follow_mode;
+    }
+
+    pub fn camera_smoothing(&self) -> &CameraSmoothing {
+        &self.camera_smoothing
+    }
+
+    pub fn set_camera_smoothing(&mut self, camera_smoothing: CameraSmoothing) {
+        self.camera_smoothing = camera_smoothing;
+    }
+
+    pub fn camera_zoom_limits(&self) -> &CameraZoomLimits {
+        &self.camera_zoom_limits
+    }
+
+    pub fn set_camera_zoom_limits(&mut self, camera_zoom_limits: CameraZoomLimits) {
+        self.camera_zoom_limits = camera_zoom_limits;
+    }
+
+    pub fn input_map(&self) -> &InputMap {
+        &self.input_map
+    }
+
+    pub fn input_map_mut(&mut self) -> &mut InputMap {
+        &mut self.input_map
+    }
+
+    pub fn gamepad_dead_zones(&self) -> &GamepadDeadZones {
+        &self.gamepad_dead_zones
+    }
+
+    pub fn set_gamepad_dead_zones(&mut self, gamepad_dead_zones: GamepadDeadZones) {
+        self.gamepad_dead_zones = gamepad_dead_zones;
+    }
+
+    /// Simulation ticks per second, clamped to [`SIMULATION_TICK_RATES`].
+    pub fn simulation_tick_rate(&self) -> f32 {
+        self.simulation_tick_rate
+            .clamp(*SIMULATION_TICK_RATES.start(), *SIMULATION_TICK_RATES.end())
+    }
+
+    pub fn set_simulation_tick_rate(&mut self, simulation_tick_rate: f32) {
+        self.simulation_tick_rate = simulation_tick_rate;
+    }
+
+    /// Fixed seed for every run, `None` picks a fresh one each time.
+    pub fn seed(&self) -> Option<u64> {
+        self.seed
+    }
+
+    pub fn set_seed(&mut self, seed: Option<u64>) {
+        self.seed = seed;
+    }
+
+    /// Camera shake strength from 0, disabled for motion sensitive players, to 1.
+    pub fn camera_shake(&self) -> f32 {
+        self.camera_shake.clamp(0.0, 1.0)
+    }
+
+    pub fn set_camera_shake(&mut self, camera_shake: f32) {
+        self.camera_shake = camera_shake;
+    }
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Whether changes may be written back to the settings file. Off when the file exists but
+/// couldn't be read, so a typo in a hand edited file isn't overwritten with defaults.
+#[derive(Resource, Debug)]
+struct SettingsWritable(bool);
+
+fn load_settings() -> (Settings, SettingsWritable) {
+    match Settings::load() {
+        Ok(settings) => (settings, SettingsWritable(true)),
+        Err(SettingsError::Io(error)) if error.kind() == std::io::ErrorKind::NotFound => {
+            (Settings::new(), SettingsWritable(true))
+        }
+        Err(error) => {
+            warn!("Using default settings without saving them: {error}");
+            (Settings::new(), SettingsWritable(false))
+        }
+    }
+}
+
+fn save_settings(settings: Res<Settings>, writable: Res<SettingsWritable>) {
+    // Freshly loaded settings are already on disk, or deliberately not written
+    if settings.is_added() || !writable.0 {
+        return;
+    }
+
+    if let Err(error) = settings.save() {
+        warn!("Failed to save settings: {error}");
+    }
+}
+
+pub struct SettingsPlugin;
+
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        let (settings, writable) = load_settings();
+
+        app.insert_resource(settings);
+        app.insert_resource(writable);
+        app.add_systems(Last, save_settings.run_if(resource_changed::<Settings>()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::{InputAction, InputBinding};
+
+    /// Movement and firing with their keyboard and mouse defaults, as written before gamepad
+    /// support.
+    const KEYBOARD_BINDINGS: &str = "
+        MoveUp: [Key(W), Key(Up)],
+        MoveDown: [Key(S), Key(Down)],
+        MoveLeft: [Key(A), Key(Left)],
+        MoveRight: [Key(D), Key(Right)],
+        FirePrimary: [Mouse(Left)],
+        FireSecondary: [Mouse(Right)],
+    ";
+
+    fn settings_file(version: Option<u32>, bindings: &str) -> String {
+        let version = version
+            .map(|version| format!("version: {version},"))
+            .unwrap_or_default();
+
+        format!("({version} input_map: (bindings: {{ {KEYBOARD_BINDINGS} {bindings} }}))")
+    }
+
+    #[test]
+    fn keyboard_only_files_gain_gamepad_bindings() {
+        let ron = settings_file(
+            Some(1),
+            "Pause: [Key(P)], Confirm: [Key(Return), Key(Space)]",
+        );
+        let settings = Settings::from_ron(&ron).unwrap();
+
+        assert_eq!(settings.input_map(), &InputMap::default());
+        assert_eq!(settings.version, SETTINGS_VERSION);
+    }
+
+    #[test]
+    fn hand_edited_bindings_win_over_gamepad_defaults() {
+        // South is Confirm's default, so Confirm keeps only its keys
+        let ron = settings_file(
+            Some(1),
+            "Pause: [Key(P), Gamepad(South)], Confirm: [Key(Return), Key(Space)]",
+        );
+        let settings = Settings::from_ron(&ron).unwrap();
+        let input_map = settings.input_map();
+
+        assert_eq!(
+            input_map.bindings(InputAction::Pause),
+            [
+                InputBinding::Key(KeyCode::P),
+                InputBinding::Gamepad(GamepadButtonType::South)
+            ]
+        );
+        assert_eq!(
+            input_map.bindings(InputAction::Confirm),
+            [
+                InputBinding::Key(KeyCode::Return),
+                InputBinding::Key(KeyCode::Space)
+            ]
+        );
+        assert!(input_map.conflicts().is_empty());
+        // Actions that were left alone still get theirs
+        assert!(input_map
+            .bindings(InputAction::FirePrimary)
+            .contains(&InputBinding::Gamepad(GamepadButtonType::RightTrigger2)));
+    }
+
+    #[test]
+    fn files_without_a_version_are_migrated_from_version_0() {
+        let ron = settings_file(None, "Pause: [Key(P)], Confirm: [Key(Return), Key(Space)]");
+        let settings = Settings::from_ron(&ron).unwrap();
+
+        assert_eq!(settings.version, SETTINGS_VERSION);
+        assert_eq!(settings.input_map(), &InputMap::default());
+    }
+}